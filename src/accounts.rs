@@ -0,0 +1,118 @@
+use std::collections::BTreeSet;
+
+/// Account whose token and settings live directly in the config directory,
+/// where they were kept before multiple accounts were supported.
+pub const DEFAULT_ACCOUNT: &str = "default";
+pub const MAX_ACCOUNT_NAME_LEN: usize = 32;
+
+/// Saved accounts, each with its own token and settings. Called profiles in commands.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Accounts {
+    pub names: BTreeSet<String>,
+    /// Account to log into on startup
+    pub last_used: String,
+}
+
+impl Default for Accounts {
+    fn default() -> Self {
+        Self {
+            names: BTreeSet::from([DEFAULT_ACCOUNT.to_string()]),
+            last_used: DEFAULT_ACCOUNT.to_string(),
+        }
+    }
+}
+
+impl Accounts {
+    pub fn contains(&self, name: &str) -> bool {
+        self.names.contains(name)
+    }
+
+    /// Parses `account <name>` and `last <name>` lines.
+    pub fn parse(text: &str) -> Self {
+        let mut res = Self::default();
+
+        for line in text.lines().map(str::trim) {
+            let Some((key, value)) = line.split_once(' ') else {
+                continue;
+            };
+
+            if validate_account_name(value).is_err() {
+                continue;
+            }
+
+            match key {
+                "account" => {
+                    res.names.insert(value.to_string());
+                }
+                "last" => res.last_used = value.to_string(),
+                _ => (),
+            }
+        }
+
+        if !res.contains(&res.last_used) {
+            res.last_used = DEFAULT_ACCOUNT.to_string();
+        }
+
+        res
+    }
+
+    pub fn serialize(&self) -> String {
+        let mut lines: Vec<String> = self
+            .names
+            .iter()
+            .filter(|name| *name != DEFAULT_ACCOUNT)
+            .map(|name| format!("account {}", name))
+            .collect();
+
+        lines.push(format!("last {}", self.last_used));
+
+        lines.join("\n")
+    }
+}
+
+/// Names are used as directory names, so only letters, digits, `-` and `_` are allowed.
+pub fn validate_account_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_ACCOUNT_NAME_LEN
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+    if !valid {
+        return Err(format!(
+            "Profile names must have 1 to {} letters, digits, - or _",
+            MAX_ACCOUNT_NAME_LEN
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::accounts::{Accounts, DEFAULT_ACCOUNT, validate_account_name};
+
+    #[test]
+    fn test_names() {
+        assert!(validate_account_name("test-bot_2").is_ok());
+        assert!(validate_account_name("").is_err());
+        assert!(validate_account_name("../tk").is_err());
+        assert!(validate_account_name("two words").is_err());
+        assert!(validate_account_name(&"x".repeat(33)).is_err());
+    }
+
+    #[test]
+    fn test_serialization() {
+        let mut accounts = Accounts::default();
+        accounts.names.insert("test".to_string());
+        accounts.names.insert("production".to_string());
+        accounts.last_used = "test".to_string();
+
+        assert_eq!(Accounts::parse(&accounts.serialize()), accounts);
+
+        // The default account is always there and unknown last accounts fall back to it
+        let accounts = Accounts::parse("account ../evil\nlast removed");
+        assert_eq!(accounts, Accounts::default());
+        assert!(accounts.contains(DEFAULT_ACCOUNT));
+    }
+}
@@ -1,37 +1,128 @@
 use core::f32;
-use std::process::exit;
+use std::{
+    collections::HashMap,
+    num::NonZeroU64,
+    path::PathBuf,
+    process::exit,
+    time::{Duration, Instant},
+};
 
 use crate::{
+    accounts::{self, Accounts, DEFAULT_ACCOUNT},
     commands::{COMMAND_PREFIX, ChatCommand, CommandContext},
+    completion::CompletionQuery,
     config,
-    discord::{self, DiscordCommEvent},
+    discord::{
+        self, AttachmentInfo, ChannelInfo, ChannelKind, ChatError, ChatMessage, ConnectionState,
+        DirectConversation, DiscordCommEvent, DiscordRequest, DiscordResponse, EmbedInfo, Mention,
+        MentionCandidate, MessageQuote, OutgoingMessage, ReactionCount, ReactionUpdate,
+        RequestEnvelope, TypingUpdate, UserSummary,
+    },
+    identity::{self, Identity},
+    outbox::{OutboxEntry, OutboxStatus},
+    ratelimit::{self, FloodGuard},
+    subscriptions::{self, Subscriptions},
+    tabs::{TabId, Tabs},
     utils,
+    webhooks::WebhookSettings,
+};
+use egui::{
+    Color32, Frame, Key, Label, Modifiers, RichText, ScrollArea, Sense, TextEdit, Ui,
+    text::{CCursor, CCursorRange},
 };
-use egui::{Color32, Frame, RichText, ScrollArea, TextEdit, Ui};
 use global_hotkey::{
     GlobalHotKeyEvent, GlobalHotKeyEventReceiver, GlobalHotKeyManager,
     hotkey::{self, HotKey},
 };
 use regex::Regex;
-use tokio::sync::mpsc::{Receiver, Sender};
+use tokio::sync::mpsc::{Receiver, UnboundedSender};
 
 enum GuiMessage {
     User(GuiUserMessage),
+    /// Local echo of an own message until Discord confirms it
+    Outgoing(GuiOutgoingMessage),
     Error(String),
     Generic(String),
 }
 
 struct GuiUserMessage {
+    id: u64,
+    channel_id: u64,
     name: String,
     content: String,
     private: bool,
+    /// Set for own direct messages, shows who they were sent to
+    recipient: Option<String>,
+    edited: bool,
+    deleted: bool,
+    reply_to: Option<MessageQuote>,
+    reactions: Vec<ReactionCount>,
+    attachments: Vec<AttachmentInfo>,
+    embeds: Vec<EmbedInfo>,
+    mentions_me: bool,
+    from_webhook: bool,
+    /// Colour from the sender's tag
+    name_color: Option<u32>,
+    /// Bot account a tagged message was sent through
+    via: Option<String>,
+}
+
+struct GuiOutgoingMessage {
+    name: String,
+    entry: OutboxEntry,
+}
+
+enum MessageAction {
+    Select,
+    Download(AttachmentInfo),
+    Retry(u64),
+    Discard(u64),
+}
+
+/// Request waiting for its response.
+struct PendingRequest {
+    id: u64,
+    request: DiscordRequest,
+    sent_at: Instant,
 }
 
+const QUOTE_PREVIEW_LEN: usize = 60;
+const SYSTEM_TAB_TITLE: &str = "Dove";
+const INVALID_CHANNEL_ID: &str = "Invalid channel ID";
+/// Requests without a response by then are reported as failed, a late response is ignored.
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
 pub struct App {
     main_frame: egui::Frame,
-    messages: Vec<GuiMessage>,
+    tabs: Tabs<GuiMessage>,
+    /// Channels opened as tabs, to switch back to them
+    tab_channels: HashMap<u64, ChannelInfo>,
     text_to_send: String,
-    tx_to_dc: Sender<DiscordCommEvent>,
+    accounts: Accounts,
+    active_channel: Option<ChannelInfo>,
+    history_loading: bool,
+    history_exhausted: bool,
+    /// The messages were scrolled to the top in the last frame
+    scrolled_to_top: bool,
+    selected_message: Option<(u64, u64)>,
+    own_user: Option<UserSummary>,
+    dm_conversations: Vec<DirectConversation>,
+    last_dm_sender: Option<u64>,
+    subscriptions: Subscriptions,
+    webhooks: WebhookSettings,
+    identity: Identity,
+    typing_users: Vec<(TypingUpdate, Instant)>,
+    last_typing_sent: Option<(u64, Instant)>,
+    mention_candidates: Vec<MentionCandidate>,
+    selected_suggestion: usize,
+    connection_state: ConnectionState,
+    connection_changed_at: Instant,
+    pending_requests: Vec<PendingRequest>,
+    last_request_id: u64,
+    /// When sending can continue, by channel or for all channels if None
+    send_delays: HashMap<Option<u64>, Instant>,
+    flood_guard: FloodGuard,
+    tx_to_dc: UnboundedSender<RequestEnvelope>,
     rx_from_dc: Receiver<DiscordCommEvent>,
     token_regex: Regex,
     token_to_save: Option<String>,
@@ -43,7 +134,10 @@ pub struct App {
 }
 
 impl App {
-    pub fn new(tx_to_dc: Sender<DiscordCommEvent>, rx_from_dc: Receiver<DiscordCommEvent>) -> Self {
+    pub fn new(
+        tx_to_dc: UnboundedSender<RequestEnvelope>,
+        rx_from_dc: Receiver<DiscordCommEvent>,
+    ) -> Self {
         let key_manager =
             GlobalHotKeyManager::new().expect("Failed to create global hot key manager");
         let key = HotKey::new(Some(hotkey::Modifiers::CONTROL), hotkey::Code::Slash);
@@ -58,23 +152,36 @@ impl App {
             rx_from_dc: rx_from_dc,
             main_frame: Frame::new(),
             text_to_send: "".to_string(),
+            accounts: Accounts::default(),
+            active_channel: None,
+            history_loading: false,
+            history_exhausted: false,
+            scrolled_to_top: false,
+            selected_message: None,
+            own_user: None,
+            dm_conversations: Vec::new(),
+            last_dm_sender: None,
+            subscriptions: Subscriptions::default(),
+            webhooks: WebhookSettings::default(),
+            identity: Identity::default(),
+            typing_users: Vec::new(),
+            last_typing_sent: None,
+            mention_candidates: Vec::new(),
+            selected_suggestion: 0,
+            connection_state: ConnectionState::LoggedOut,
+            connection_changed_at: Instant::now(),
+            pending_requests: Vec::new(),
+            last_request_id: 0,
+            send_delays: HashMap::new(),
+            flood_guard: FloodGuard::default(),
             global_key_manager: key_manager,
             global_key_receiver: key_receiver,
             open_chat_hotkey: key,
             token_to_save: None,
             token_regex: Regex::new(r"[A-Za-z0-9_-]{16,}\.[A-Za-z0-9_-]{5,}\.[A-Za-z0-9_-]{16,}")
                 .expect("Invalid regex pattern for token"),
-            messages: vec![
-                GuiMessage::Generic("Welcome to Dove".to_string()),
-                GuiMessage::Generic("Contact Wolfyxon if you need help or find bugs".to_string()),
-                GuiMessage::Generic(
-                    "Please note that this is an early test version and things may change soon.\n"
-                        .to_string(),
-                ),
-                GuiMessage::Generic("Use /help to see a list of commands".to_string()),
-                GuiMessage::Generic("Use /login <token> to log into the chat".to_string()),
-                GuiMessage::Generic("Do not show your token to anyone!\n".to_string()),
-            ],
+            tabs: Tabs::new(SYSTEM_TAB_TITLE),
+            tab_channels: HashMap::new(),
             commands: vec![
                 ChatCommand::one_alias("help")
                     .with_description("Shows a list of commands")
@@ -85,12 +192,59 @@ impl App {
                 ChatCommand::one_alias("logout")
                     .with_description("Logs out of Discord and forgets your token")
                     .with_handler(Self::cmd_logout),
+                ChatCommand::one_alias("profile")
+                    .with_alias("account")
+                    .with_description(
+                        "Manages saved accounts, each with its own token and settings: /profile list|add|use|remove <name>",
+                    )
+                    .with_handler(Self::cmd_profile),
                 ChatCommand::one_alias("servers")
                     .with_description("Shows a list of available servers")
                     .with_handler(Self::cmd_list_guilds),
                 ChatCommand::one_alias("channels")
                     .with_description("Shows a list of available channels in a given server")
                     .with_handler(Self::cmd_list_channels),
+                ChatCommand::one_alias("join")
+                    .with_description(
+                        "Selects the channel to chat in: /join <channel-id> or /join <server> #<channel>",
+                    )
+                    .with_handler(Self::cmd_join),
+                ChatCommand::one_alias("watch")
+                    .with_description(
+                        "Shows messages from a channel besides the active one: /watch <channel-id|dms>",
+                    )
+                    .with_handler(Self::cmd_watch),
+                ChatCommand::one_alias("unwatch")
+                    .with_description("Stops showing messages from a watched channel: /unwatch <channel-id|dms>")
+                    .with_handler(Self::cmd_unwatch),
+                ChatCommand::one_alias("react")
+                    .with_description("Reacts to the selected or last message: /react <emoji>")
+                    .with_handler(Self::cmd_react),
+                ChatCommand::one_alias("unreact")
+                    .with_description("Removes your reaction from the selected or last message")
+                    .with_handler(Self::cmd_unreact),
+                ChatCommand::one_alias("dm")
+                    .with_description("Sends a direct message: /dm <user-id|name> <text>")
+                    .with_handler(Self::cmd_dm),
+                ChatCommand::one_alias("r")
+                    .with_description("Replies to the last direct message: /r <text>")
+                    .with_handler(Self::cmd_reply_dm),
+                ChatCommand::one_alias("dms")
+                    .with_description("Shows a list of direct message conversations")
+                    .with_handler(Self::cmd_list_dms),
+                ChatCommand::one_alias("nick")
+                    .with_description(
+                        "Sets the name others see on your messages: /nick <name>|clear|color <#rrggbb|none>",
+                    )
+                    .with_handler(Self::cmd_nick),
+                ChatCommand::one_alias("webhook")
+                    .with_description(
+                        "Sends your messages in the active channel under your nickname: /webhook on|off|avatar <url|none>",
+                    )
+                    .with_handler(Self::cmd_webhook),
+                ChatCommand::one_alias("upload")
+                    .with_description("Sends a file to the active channel: /upload <path> [caption], quote paths with spaces")
+                    .with_handler(Self::cmd_upload),
                 ChatCommand::one_alias("clear")
                     .with_description("Clears the chat")
                     .with_handler(Self::cmd_clear),
@@ -100,14 +254,325 @@ impl App {
             ],
         };
 
+        app.tabs.system_mut().messages = vec![
+            GuiMessage::Generic("Welcome to Dove".to_string()),
+            GuiMessage::Generic("Contact Wolfyxon if you need help or find bugs".to_string()),
+            GuiMessage::Generic(
+                "Please note that this is an early test version and things may change soon.\n"
+                    .to_string(),
+            ),
+            GuiMessage::Generic("Use /help to see a list of commands".to_string()),
+            GuiMessage::Generic("Use /login <token> to log into the chat".to_string()),
+            GuiMessage::Generic("Do not show your token to anyone!\n".to_string()),
+        ];
+
+        app.load_accounts();
+        app.load_settings();
         app.auto_login();
 
         app
     }
 
+    fn cmd_join(&mut self, ctx: CommandContext) {
+        let input = ctx.args.join(" ");
+
+        match input.rsplit_once('#') {
+            Some((guild_query, channel_name)) => {
+                let guild_query = guild_query.trim();
+                let channel_name = channel_name.trim();
+
+                if guild_query.is_empty() || channel_name.is_empty() {
+                    self.add_message(GuiMessage::Error(
+                        "Usage: /join <server> #<channel>".to_string(),
+                    ));
+                    return;
+                }
+
+                self.transmit_to_dc(DiscordRequest::JoinChannelByName(
+                    guild_query.to_string(),
+                    channel_name.to_string(),
+                ));
+            }
+            None => match str::parse::<NonZeroU64>(input.trim()) {
+                Ok(channel_id) => {
+                    self.transmit_to_dc(DiscordRequest::JoinChannel(channel_id.get()))
+                }
+                Err(_e) => self.add_message(GuiMessage::Error(format!(
+                    "{}. Use /join <channel-id> or /join <server> #<channel>",
+                    INVALID_CHANNEL_ID
+                ))),
+            },
+        }
+    }
+
+    fn cmd_watch(&mut self, ctx: CommandContext) {
+        let Some(arg) = ctx.args.first() else {
+            self.list_subscriptions();
+            return;
+        };
+
+        if arg == subscriptions::DMS_KEYWORD {
+            self.subscriptions.direct_messages = true;
+            self.add_message(GuiMessage::Generic("Watching direct messages".to_string()));
+        } else {
+            match str::parse::<NonZeroU64>(arg) {
+                Ok(channel_id) => {
+                    self.subscriptions.channels.insert(channel_id.get());
+                    self.add_message(GuiMessage::Generic(format!(
+                        "Watching channel {}",
+                        channel_id
+                    )));
+                }
+                Err(_e) => {
+                    self.add_message(GuiMessage::Error(INVALID_CHANNEL_ID.to_string()));
+                    return;
+                }
+            }
+        }
+
+        self.save_subscriptions();
+    }
+
+    fn cmd_unwatch(&mut self, ctx: CommandContext) {
+        let Some(arg) = ctx.args.first() else {
+            self.add_message(GuiMessage::Error(
+                "No channel specified. Use /watch to see watched channels".to_string(),
+            ));
+            return;
+        };
+
+        if arg == subscriptions::DMS_KEYWORD {
+            self.subscriptions.direct_messages = false;
+            self.add_message(GuiMessage::Generic(
+                "No longer watching direct messages".to_string(),
+            ));
+        } else {
+            match str::parse::<NonZeroU64>(arg) {
+                Ok(channel_id) => {
+                    if !self.subscriptions.channels.remove(&channel_id.get()) {
+                        self.add_message(GuiMessage::Error(format!(
+                            "Channel {} is not watched",
+                            channel_id
+                        )));
+                        return;
+                    }
+
+                    self.add_message(GuiMessage::Generic(format!(
+                        "No longer watching channel {}",
+                        channel_id
+                    )));
+                }
+                Err(_e) => {
+                    self.add_message(GuiMessage::Error(INVALID_CHANNEL_ID.to_string()));
+                    return;
+                }
+            }
+        }
+
+        self.save_subscriptions();
+    }
+
+    fn cmd_react(&mut self, ctx: CommandContext) {
+        if let Some((channel_id, message_id, emoji)) = self.get_reaction_target(ctx) {
+            self.transmit_to_dc(DiscordRequest::ReactionAdd(channel_id, message_id, emoji));
+        }
+    }
+
+    fn cmd_unreact(&mut self, ctx: CommandContext) {
+        if let Some((channel_id, message_id, emoji)) = self.get_reaction_target(ctx) {
+            self.transmit_to_dc(DiscordRequest::ReactionRemove(
+                channel_id, message_id, emoji,
+            ));
+        }
+    }
+
+    fn get_reaction_target(&mut self, ctx: CommandContext) -> Option<(u64, u64, String)> {
+        let Some(emoji) = ctx.args.first() else {
+            self.add_message(GuiMessage::Error("No emoji specified".to_string()));
+            return None;
+        };
+
+        match self.get_target_message() {
+            Some((channel_id, message_id)) => Some((channel_id, message_id, emoji.to_owned())),
+            None => {
+                self.add_message(GuiMessage::Error(
+                    "No message to react to. Select one with a click or Alt+Up".to_string(),
+                ));
+                None
+            }
+        }
+    }
+
+    /// Returns the selected message or the latest one in the active channel.
+    fn get_target_message(&self) -> Option<(u64, u64)> {
+        if self.selected_message.is_some() {
+            return self.selected_message;
+        }
+
+        let channel_id = self.active_channel.as_ref()?.id;
+        let tab = self.tabs.get(TabId::Channel(channel_id))?;
+
+        tab.messages.iter().rev().find_map(|msg| match msg {
+            GuiMessage::User(msg) if msg.channel_id == channel_id && !msg.deleted => {
+                Some((msg.channel_id, msg.id))
+            }
+            _ => None,
+        })
+    }
+
+    fn cmd_dm(&mut self, ctx: CommandContext) {
+        if ctx.args.len() < 2 {
+            self.add_message(GuiMessage::Error(
+                "Usage: /dm <user-id|name> <text>".to_string(),
+            ));
+            return;
+        }
+
+        let recipient = ctx.args[0].to_owned();
+        let text = ctx.args[1..].join(" ");
+
+        self.send_direct_message(recipient, text);
+    }
+
+    fn cmd_reply_dm(&mut self, ctx: CommandContext) {
+        let Some(user_id) = self.last_dm_sender else {
+            self.add_message(GuiMessage::Error(
+                "Nobody sent you a direct message yet".to_string(),
+            ));
+            return;
+        };
+
+        if ctx.args.is_empty() {
+            self.add_message(GuiMessage::Error("Usage: /r <text>".to_string()));
+            return;
+        }
+
+        self.send_direct_message(user_id.to_string(), ctx.args.join(" "));
+    }
+
+    fn cmd_list_dms(&mut self, _ctx: CommandContext) {
+        let mut msgs: Vec<GuiMessage> = Vec::new();
+
+        for conversation in self.dm_conversations.iter().rev() {
+            let last = if self.last_dm_sender == Some(conversation.user.id) {
+                " (last sender)"
+            } else {
+                ""
+            };
+
+            msgs.push(GuiMessage::Generic(format!(
+                " {}: {}{}",
+                conversation.user.id, conversation.user.name, last
+            )));
+        }
+
+        self.add_message(GuiMessage::Generic("Direct messages:".to_string()));
+
+        for msg in msgs {
+            self.add_message(msg);
+        }
+    }
+
+    fn send_direct_message(&mut self, recipient: String, text: String) {
+        if self.token_regex.is_match(&text) {
+            self.add_message(GuiMessage::Error(
+                "Your message was not sent, because it possibly contained Discord token."
+                    .to_string(),
+            ));
+            return;
+        }
+
+        if self.is_flood(&text) {
+            return;
+        }
+
+        let parts = self.outgoing_parts(&text, true);
+
+        // Known conversations don't have to be looked up, so their messages can wait offline
+        let known = self
+            .dm_conversations
+            .iter()
+            .find(|c| {
+                c.user.id.to_string() == recipient || c.user.name.eq_ignore_ascii_case(&recipient)
+            })
+            .cloned();
+
+        let Some(conversation) = known else {
+            self.transmit_to_dc(DiscordRequest::DirectMessageSend(recipient, parts));
+            return;
+        };
+
+        for part in parts {
+            self.transmit_to_dc(DiscordRequest::MessageSend(OutgoingMessage {
+                channel_id: conversation.channel_id,
+                content: part,
+                reply_to: None,
+                attachments: Vec::new(),
+                webhook: None,
+            }));
+        }
+
+        self.track_dm_conversation(conversation);
+    }
+
+    /// Moves the conversation to the end of the list, so the most recent one is last.
+    fn track_dm_conversation(&mut self, conversation: DirectConversation) {
+        // The own message may have opened the tab before the conversation was known
+        if let Some(tab) = self.tabs.get_mut(TabId::Channel(conversation.channel_id)) {
+            tab.title = format!("@{}", conversation.user.name);
+        }
+
+        self.dm_conversations
+            .retain(|c| c.channel_id != conversation.channel_id);
+        self.dm_conversations.push(conversation);
+    }
+
+    fn is_own_message(&self, msg: &ChatMessage) -> bool {
+        self.own_user
+            .as_ref()
+            .is_some_and(|user| user.id == msg.author_id)
+    }
+
+    fn cmd_upload(&mut self, ctx: CommandContext) {
+        let Some((path, caption)) = utils::text::split_first_arg(&ctx.raw_args) else {
+            self.add_message(GuiMessage::Error(
+                "The file path is missing its closing quote".to_string(),
+            ));
+            return;
+        };
+
+        if path.is_empty() {
+            self.add_message(GuiMessage::Error(
+                "No file specified. Put paths with spaces in quotes: /upload \"<path>\" [caption]"
+                    .to_string(),
+            ));
+            return;
+        }
+
+        if self.token_regex.is_match(caption) {
+            self.add_message(GuiMessage::Error(
+                "Your message was not sent, because it possibly contained Discord token."
+                    .to_string(),
+            ));
+            return;
+        }
+
+        let path = PathBuf::from(path);
+
+        if !path.is_file() {
+            self.add_message(GuiMessage::Error(format!(
+                "File '{}' not found",
+                path.display()
+            )));
+            return;
+        }
+
+        self.send_to_active_channel(caption.to_string(), vec![path]);
+    }
+
     fn cmd_logout(&mut self, _ctx: CommandContext) {
-        if config::get_token_file_path().exists() {
-            config::delete_token_file().unwrap_or_else(|e| {
+        if config::get_token_file_path(&self.accounts.last_used).exists() {
+            config::delete_token_file(&self.accounts.last_used).unwrap_or_else(|e| {
                 self.add_message(GuiMessage::Error(format!(
                     "Unable to forget your token: {}",
                     e
@@ -115,7 +580,8 @@ impl App {
             });
         }
 
-        self.transmit_to_dc(DiscordCommEvent::Logout);
+        self.transmit_to_dc(DiscordRequest::Logout);
+        self.own_user = None;
 
         self.add_message(GuiMessage::Generic("Logged out".to_string()));
     }
@@ -155,16 +621,15 @@ impl App {
     }
 
     fn cmd_list_guilds(&mut self, _ctx: CommandContext) {
-        self.transmit_to_dc(DiscordCommEvent::GetGuilds);
+        self.transmit_to_dc(DiscordRequest::GetGuilds);
     }
 
     fn cmd_list_channels(&mut self, ctx: CommandContext) {
         match ctx.args.get(0) {
             Some(guild_id) => {
-                match str::parse::<u64>(guild_id) {
-                    Ok(guild_id) => {
-                        self.transmit_to_dc(DiscordCommEvent::GetAvailableTextChannels(guild_id))
-                    }
+                match str::parse::<NonZeroU64>(guild_id) {
+                    Ok(guild_id) => self
+                        .transmit_to_dc(DiscordRequest::GetAvailableTextChannels(guild_id.get())),
                     Err(_e) => self.add_message(GuiMessage::Error("Invalid server ID".to_string())),
                 };
             }
@@ -195,7 +660,7 @@ impl App {
     }
 
     fn cmd_clear(&mut self, _ctx: CommandContext) {
-        self.messages.clear();
+        self.tabs.selected_mut().messages.clear();
     }
 
     fn cmd_exit(&mut self, _ctx: CommandContext) {
@@ -214,15 +679,22 @@ impl App {
 
     fn login(&mut self, token: String) {
         self.add_message(GuiMessage::Generic("Logging in...".to_string()));
-        self.transmit_to_dc(DiscordCommEvent::Login(token));
+        self.transmit_to_dc(DiscordRequest::Login(token));
     }
 
     fn auto_login(&mut self) {
-        if !config::get_token_file_path().exists() {
+        if !config::get_token_file_path(&self.accounts.last_used).exists() {
             return;
         }
 
-        match config::get_token() {
+        if self.accounts.last_used != DEFAULT_ACCOUNT {
+            self.add_message(GuiMessage::Generic(format!(
+                "Using profile {}",
+                self.accounts.last_used
+            )));
+        }
+
+        match config::get_token(&self.accounts.last_used) {
             Ok(token) => {
                 self.login(token);
             }
@@ -232,96 +704,1651 @@ impl App {
         };
     }
 
-    fn add_message(&mut self, msg: GuiMessage) {
-        self.messages.push(msg);
+    /// Loads the settings of the active account.
+    fn load_settings(&mut self) {
+        self.load_active_channel();
+        self.load_subscriptions();
+        self.load_webhook_settings();
+        self.load_identity();
+
+        // Webhook messages had their own name before nicknames existed
+        if let Some(name) = self.webhooks.legacy_name.take() {
+            if self.identity.nickname.is_none() && identity::validate_nickname(&name).is_ok() {
+                self.identity.nickname = Some(name);
+                self.save_identity();
+            }
+
+            self.save_webhook_settings();
+        }
     }
 
-    fn transmit_to_dc(&mut self, event: DiscordCommEvent) {
-        let tx = self.tx_to_dc.to_owned();
+    /// Restores the last used account, its files are used from now on.
+    fn load_accounts(&mut self) {
+        if config::get_accounts_file_path().exists() {
+            match config::get_accounts() {
+                Ok(accounts) => self.accounts = accounts,
+                Err(e) => {
+                    self.add_message(GuiMessage::Error(format!(
+                        "Unable to load saved profiles: {}",
+                        e
+                    )));
+                }
+            }
+        }
 
-        tokio::spawn(async move {
-            tx.send(event)
-                .await
-                .expect("Failed to send event to Discord thread");
-        });
+        self.transmit_to_dc(DiscordRequest::SwitchAccount(
+            self.accounts.last_used.clone(),
+        ));
     }
 
-    fn clear_message(&mut self) {
-        self.text_to_send = String::new();
+    fn save_accounts(&mut self) {
+        if let Err(e) = config::save_accounts(&self.accounts) {
+            self.add_message(GuiMessage::Error(format!("Unable to save profiles: {}", e)));
+        }
     }
 
-    fn process_command(&mut self, input: String) {
-        let split: Vec<&str> = input.split_whitespace().collect();
+    fn cmd_profile(&mut self, ctx: CommandContext) {
+        let name = ctx.args.get(1).map(String::as_str).unwrap_or_default();
 
-        if split.is_empty() {
-            return;
+        match ctx.args.first().map(String::as_str) {
+            None | Some("list") => self.list_accounts(),
+            Some("add") => {
+                if let Err(e) = accounts::validate_account_name(name) {
+                    self.add_message(GuiMessage::Error(e));
+                    return;
+                }
+
+                if self.accounts.contains(name) {
+                    self.add_message(GuiMessage::Error(format!(
+                        "Profile {} already exists, switch to it with /profile use {}",
+                        name, name
+                    )));
+                    return;
+                }
+
+                self.accounts.names.insert(name.to_string());
+                self.switch_account(name);
+            }
+            Some("use") => {
+                if !self.accounts.contains(name) {
+                    self.add_message(GuiMessage::Error(format!(
+                        "No profile named '{}'. Use /profile list to see saved profiles",
+                        name
+                    )));
+                    return;
+                }
+
+                self.switch_account(name);
+            }
+            Some("remove") => self.remove_account(name),
+            Some(_) => {
+                self.add_message(GuiMessage::Error(
+                    "Usage: /profile list|add|use|remove <name>".to_string(),
+                ));
+            }
         }
+    }
 
-        let alias = split[0];
-        let args: Vec<String> = split[1..].iter().map(|s| s.to_string()).collect();
+    fn list_accounts(&mut self) {
+        let mut msgs: Vec<GuiMessage> = Vec::new();
 
-        let cmd = self.get_command(alias.to_string());
+        for name in &self.accounts.names {
+            let mut tags: Vec<&str> = Vec::new();
 
-        if let Some(cmd) = cmd {
-            let ctx = CommandContext { args };
+            if *name == self.accounts.last_used {
+                tags.push("active");
+            }
 
-            cmd.execute(self, ctx);
+            if !config::has_saved_token(name) {
+                tags.push("no token");
+            }
+
+            let text = match tags.is_empty() {
+                true => format!(" {}", name),
+                false => format!(" {} [{}]", name, tags.join(", ")),
+            };
+
+            msgs.push(GuiMessage::Generic(text));
+        }
+
+        self.add_message(GuiMessage::Generic("Saved profiles:".to_string()));
+
+        for msg in msgs {
+            self.add_message(msg);
+        }
+    }
+
+    /// Logs out, then loads the account's settings and logs in with its token.
+    fn switch_account(&mut self, name: &str) {
+        if self.own_user.is_some() || self.token_to_save.is_some() {
+            self.transmit_to_dc(DiscordRequest::Logout);
+        }
+
+        self.accounts.last_used = name.to_string();
+        self.save_accounts();
+        self.transmit_to_dc(DiscordRequest::SwitchAccount(name.to_string()));
+
+        // Nothing from the previous account applies anymore
+        self.tabs.clear();
+        self.tab_channels.clear();
+        self.own_user = None;
+        self.token_to_save = None;
+        self.active_channel = None;
+        self.history_loading = false;
+        self.history_exhausted = false;
+        self.selected_message = None;
+        self.dm_conversations.clear();
+        self.last_dm_sender = None;
+        self.subscriptions = Subscriptions::default();
+        self.webhooks = WebhookSettings::default();
+        self.identity = Identity::default();
+        self.typing_users.clear();
+        self.last_typing_sent = None;
+        self.mention_candidates.clear();
+        self.send_delays.clear();
+
+        self.add_message(GuiMessage::Generic(format!("Switched to profile {}", name)));
+        self.load_settings();
+
+        if config::get_token_file_path(&self.accounts.last_used).exists() {
+            self.auto_login();
         } else {
-            self.add_message(GuiMessage::Error(format!("Unknown command '{}'", alias)));
+            self.add_message(GuiMessage::Generic(
+                "Use /login <token> to log into this profile".to_string(),
+            ));
         }
     }
 
-    fn submit_message(&mut self) {
-        let text = self.text_to_send.to_owned();
+    fn remove_account(&mut self, name: &str) {
+        if !self.accounts.contains(name) {
+            self.add_message(GuiMessage::Error(format!("No profile named '{}'", name)));
+            return;
+        }
 
-        if text.trim().is_empty() {
+        if name == self.accounts.last_used {
+            self.add_message(GuiMessage::Error(
+                "You can't remove the profile in use, switch to another one first".to_string(),
+            ));
             return;
         }
 
-        if text.starts_with(COMMAND_PREFIX) {
-            let cmd_text = &text[COMMAND_PREFIX.len()..];
+        let path = config::get_account_dir(name);
 
-            self.clear_message();
-            self.process_command(cmd_text.to_string());
+        // The default account's directory holds everything else
+        let exists = match name {
+            DEFAULT_ACCOUNT => config::has_saved_token(name),
+            _ => path.exists(),
+        };
 
+        if exists && let Err(e) = config::delete_account(name) {
+            self.add_message(GuiMessage::Error(format!(
+                "Unable to remove profile {}: {}",
+                name, e
+            )));
             return;
         }
 
-        if self.token_regex.is_match(&text) {
-            self.add_message(GuiMessage::Error(
-                "Your message was not sent, because it possibly contained Discord token."
-                    .to_string(),
+        if name == DEFAULT_ACCOUNT {
+            self.add_message(GuiMessage::Generic(
+                "The token of the default profile was forgotten".to_string(),
             ));
             return;
         }
 
-        self.transmit_to_dc(DiscordCommEvent::MessageSend(
-            1459160075649286318,
-            text.to_owned(),
-        ));
-
-        //self.add_message(GuiMessage::User("local".to_string(), text.to_string()));
-        self.clear_message();
+        self.accounts.names.remove(name);
+        self.save_accounts();
+        self.add_message(GuiMessage::Generic(format!("Removed profile {}", name)));
     }
 
-    fn poll_global_hotkeys(&mut self) -> bool {
-        if let Ok(event) = self.global_key_receiver.try_recv() {
-            if event.id == self.open_chat_hotkey.id {
-                return true;
-            }
+    fn load_active_channel(&mut self) {
+        if !config::get_active_channel_file_path(&self.accounts.last_used).exists() {
+            return;
         }
 
-        false
+        match config::get_active_channel(&self.accounts.last_used) {
+            Ok(channel) => {
+                self.open_channel_tab(&channel);
+                self.tabs.select(TabId::Channel(channel.id));
+                self.active_channel = Some(channel);
+            }
+            Err(e) => {
+                self.add_message(GuiMessage::Error(format!(
+                    "Unable to restore the last channel: {}",
+                    e
+                )));
+            }
+        }
     }
 
-    fn poll_discord_events(&mut self) {
-        match self.rx_from_dc.try_recv() {
-            Ok(event) => match event {
-                DiscordCommEvent::Ready => {
-                    self.add_message(GuiMessage::Generic("Logged in successfully".to_string()));
+    /// Lists channels with their threads and forum posts indented below them.
+    fn list_channels(&mut self, channels: Vec<ChannelInfo>) {
+        let (threads, channels): (Vec<ChannelInfo>, Vec<ChannelInfo>) = channels
+            .into_iter()
+            .partition(|c| c.kind == ChannelKind::Thread);
 
-                    if let Some(token) = &self.token_to_save {
-                        match config::save_token(token.to_owned()) {
+        let mut msgs: Vec<GuiMessage> = Vec::new();
+
+        for channel in &channels {
+            msgs.push(GuiMessage::Generic(format!(
+                " {}: #{} [{}]",
+                channel.id,
+                channel.name,
+                Self::channel_tags(channel, channel.kind.label())
+            )));
+
+            let thread_label = match channel.kind {
+                ChannelKind::Forum => "post",
+                _ => ChannelKind::Thread.label(),
+            };
+
+            for thread in threads.iter().filter(|t| t.parent_id == Some(channel.id)) {
+                msgs.push(GuiMessage::Generic(format!(
+                    "   └ {}: {} [{}]",
+                    thread.id,
+                    thread.name,
+                    Self::channel_tags(thread, thread_label)
+                )));
+            }
+        }
+
+        // Threads of channels that aren't listed
+        for thread in threads
+            .iter()
+            .filter(|t| !channels.iter().any(|c| Some(c.id) == t.parent_id))
+        {
+            msgs.push(GuiMessage::Generic(format!(
+                " {}: {} [{}]",
+                thread.id,
+                thread.name,
+                Self::channel_tags(thread, thread.kind.label())
+            )));
+        }
+
+        self.add_message(GuiMessage::Generic("Available channels:".to_string()));
+
+        for msg in msgs {
+            self.add_message(msg);
+        }
+    }
+
+    fn channel_tags(channel: &ChannelInfo, kind_label: &str) -> String {
+        let mut tags = vec![kind_label];
+
+        // Forums can't be sent to directly, only their posts
+        if !channel.permissions.send && channel.kind != ChannelKind::Forum {
+            tags.push("read-only");
+        }
+
+        if !channel.permissions.read_history {
+            tags.push("no history");
+        }
+
+        tags.join(", ")
+    }
+
+    fn load_subscriptions(&mut self) {
+        if !config::get_subscriptions_file_path(&self.accounts.last_used).exists() {
+            return;
+        }
+
+        match config::get_subscriptions(&self.accounts.last_used) {
+            Ok(subscriptions) => self.subscriptions = subscriptions,
+            Err(e) => {
+                self.add_message(GuiMessage::Error(format!(
+                    "Unable to load watched channels: {}",
+                    e
+                )));
+            }
+        }
+    }
+
+    fn save_subscriptions(&mut self) {
+        if let Err(e) = config::save_subscriptions(&self.accounts.last_used, &self.subscriptions) {
+            self.add_message(GuiMessage::Error(format!(
+                "Unable to save watched channels: {}",
+                e
+            )));
+        }
+    }
+
+    fn load_identity(&mut self) {
+        if !config::get_identity_file_path(&self.accounts.last_used).exists() {
+            return;
+        }
+
+        match config::get_identity(&self.accounts.last_used) {
+            Ok(identity) => self.identity = identity,
+            Err(e) => {
+                self.add_message(GuiMessage::Error(format!(
+                    "Unable to load your nickname: {}",
+                    e
+                )));
+            }
+        }
+    }
+
+    fn save_identity(&mut self) {
+        if let Err(e) = config::save_identity(&self.accounts.last_used, &self.identity) {
+            self.add_message(GuiMessage::Error(format!(
+                "Unable to save your nickname: {}",
+                e
+            )));
+        }
+    }
+
+    fn cmd_nick(&mut self, ctx: CommandContext) {
+        let value = ctx.args.join(" ");
+
+        match ctx.args.first().map(String::as_str) {
+            None => {
+                let text = match (&self.identity.nickname, self.identity.color) {
+                    (Some(name), Some(color)) => format!(
+                        "Your nickname is {} ({})",
+                        name,
+                        identity::format_color(color)
+                    ),
+                    (Some(name), None) => format!("Your nickname is {}", name),
+                    (None, _) => "No nickname set, messages show the bot's name. Use /nick <name>"
+                        .to_string(),
+                };
+
+                self.add_message(GuiMessage::Generic(text));
+                return;
+            }
+            Some("clear") => {
+                self.identity.nickname = None;
+                self.add_message(GuiMessage::Generic("Nickname cleared".to_string()));
+            }
+            Some("color") => {
+                let arg = ctx.args.get(1).map(String::as_str).unwrap_or_default();
+
+                if arg == "none" {
+                    self.identity.color = None;
+                    self.add_message(GuiMessage::Generic("Name colour cleared".to_string()));
+                } else if let Some(color) = identity::parse_color(arg) {
+                    self.identity.color = Some(color);
+                    self.add_message(GuiMessage::Generic(format!(
+                        "Name colour set to {}",
+                        identity::format_color(color)
+                    )));
+                } else {
+                    self.add_message(GuiMessage::Error(
+                        "Invalid colour. Use /nick color #rrggbb, e.g. #ff8800".to_string(),
+                    ));
+                    return;
+                }
+            }
+            Some(_) => {
+                if let Err(e) = identity::validate_nickname(&value) {
+                    self.add_message(GuiMessage::Error(e));
+                    return;
+                }
+
+                self.add_message(GuiMessage::Generic(format!(
+                    "Your messages will show {} as the sender",
+                    value
+                )));
+                self.identity.nickname = Some(value);
+            }
+        }
+
+        self.save_identity();
+    }
+
+    fn load_webhook_settings(&mut self) {
+        if !config::get_webhooks_file_path(&self.accounts.last_used).exists() {
+            return;
+        }
+
+        match config::get_webhook_settings(&self.accounts.last_used) {
+            Ok(settings) => self.webhooks = settings,
+            Err(e) => {
+                self.add_message(GuiMessage::Error(format!(
+                    "Unable to load webhook settings: {}",
+                    e
+                )));
+            }
+        }
+    }
+
+    fn save_webhook_settings(&mut self) {
+        if let Err(e) = config::save_webhook_settings(&self.accounts.last_used, &self.webhooks) {
+            self.add_message(GuiMessage::Error(format!(
+                "Unable to save webhook settings: {}",
+                e
+            )));
+        }
+    }
+
+    fn cmd_webhook(&mut self, ctx: CommandContext) {
+        let value = ctx.args.get(1..).unwrap_or_default().join(" ");
+
+        match ctx.args.first().map(String::as_str) {
+            None => self.show_webhook_settings(),
+            Some("on") => self.enable_webhook(),
+            Some("off") => {
+                let Some(channel) = &self.active_channel else {
+                    self.add_message(GuiMessage::Error("No channel selected".to_string()));
+                    return;
+                };
+
+                let label = Self::channel_label(channel);
+                self.webhooks.channels.remove(&channel.id);
+                self.add_message(GuiMessage::Generic(format!(
+                    "Messages in {} are sent as the bot again",
+                    label
+                )));
+            }
+            Some("avatar") => {
+                let url = value.trim();
+
+                if url.is_empty() || url == "none" {
+                    self.webhooks.avatar_url = None;
+                    self.add_message(GuiMessage::Generic("Avatar removed".to_string()));
+                } else if url.starts_with("https://") || url.starts_with("http://") {
+                    self.webhooks.avatar_url = Some(url.to_string());
+                    self.add_message(GuiMessage::Generic("Avatar set".to_string()));
+                } else {
+                    self.add_message(GuiMessage::Error(
+                        "The avatar must be an image URL".to_string(),
+                    ));
+                    return;
+                }
+            }
+            Some("name") => {
+                self.add_message(GuiMessage::Error(
+                    "Webhook messages use your nickname, set it with /nick <name>".to_string(),
+                ));
+                return;
+            }
+            Some(_) => {
+                self.add_message(GuiMessage::Error(
+                    "Usage: /webhook on|off|avatar <url|none>".to_string(),
+                ));
+                return;
+            }
+        }
+
+        self.save_webhook_settings();
+    }
+
+    fn enable_webhook(&mut self) {
+        let Some(channel) = &self.active_channel else {
+            self.add_message(GuiMessage::Error(
+                "No channel selected. Join the channel to use webhooks in first".to_string(),
+            ));
+            return;
+        };
+
+        if channel.permissions.webhooks {
+            self.webhooks.channels.insert(channel.id);
+        } else {
+            let text = format!(
+                "Webhooks can't be used in {}, the bot is missing the Manage Webhooks permission",
+                Self::channel_label(channel)
+            );
+            self.add_message(GuiMessage::Error(text));
+            return;
+        }
+
+        let label = Self::channel_label(channel);
+
+        match &self.identity.nickname {
+            Some(name) => {
+                let text = format!("Messages in {} will be sent as {}", label, name);
+                self.add_message(GuiMessage::Generic(text));
+            }
+            None => {
+                let text = format!(
+                    "Webhook mode is on in {}, set your nickname with /nick <name> to use it",
+                    label
+                );
+                self.add_message(GuiMessage::Generic(text));
+            }
+        }
+    }
+
+    fn show_webhook_settings(&mut self) {
+        let name = self
+            .identity
+            .nickname
+            .as_deref()
+            .unwrap_or("not set, use /nick <name>");
+        let avatar = self.webhooks.avatar_url.as_deref().unwrap_or("none");
+        let name_text = format!("Webhook name: {}", name);
+        let avatar_text = format!("Webhook avatar: {}", avatar);
+        let channels: Vec<String> = self
+            .webhooks
+            .channels
+            .iter()
+            .map(|id| id.to_string())
+            .collect();
+
+        self.add_message(GuiMessage::Generic(name_text));
+        self.add_message(GuiMessage::Generic(avatar_text));
+
+        if channels.is_empty() {
+            self.add_message(GuiMessage::Generic(
+                "No channels use webhooks. Use /webhook on in a channel".to_string(),
+            ));
+        } else {
+            self.add_message(GuiMessage::Generic(format!(
+                "Channels using webhooks: {}",
+                channels.join(", ")
+            )));
+        }
+    }
+
+    fn list_subscriptions(&mut self) {
+        let mut msgs: Vec<GuiMessage> = Vec::new();
+
+        if let Some(channel) = &self.active_channel {
+            msgs.push(GuiMessage::Generic(format!(
+                " {} (active)",
+                Self::channel_label(channel)
+            )));
+        }
+
+        for channel_id in &self.subscriptions.channels {
+            msgs.push(GuiMessage::Generic(format!(" #{}", channel_id)));
+        }
+
+        if self.subscriptions.direct_messages {
+            msgs.push(GuiMessage::Generic(" Direct messages".to_string()));
+        }
+
+        self.add_message(GuiMessage::Generic("Watched channels:".to_string()));
+
+        for msg in msgs {
+            self.add_message(msg);
+        }
+    }
+
+    fn is_subscribed(&self, msg: &ChatMessage) -> bool {
+        let active_channel = self.active_channel.as_ref().map(|c| c.id);
+
+        // Conversations the user took part in stay visible even when direct messages aren't watched
+        if self.dm_conversation(msg.channel_id).is_some() {
+            return true;
+        }
+
+        // Open tabs keep receiving messages after switching away from them
+        if self.tabs.get(TabId::Channel(msg.channel_id)).is_some() {
+            return true;
+        }
+
+        self.subscriptions
+            .is_subscribed(msg.channel_id, msg.guild_id.is_none(), active_channel)
+    }
+
+    fn set_active_channel(&mut self, channel: ChannelInfo) {
+        self.add_message(GuiMessage::Generic(format!(
+            "Now chatting in {}",
+            Self::channel_label(&channel)
+        )));
+
+        self.activate_channel(channel);
+    }
+
+    /// Makes the channel the one messages are sent to and shows its tab.
+    fn activate_channel(&mut self, channel: ChannelInfo) {
+        if let Err(e) = config::save_active_channel(&self.accounts.last_used, &channel) {
+            self.add_message(GuiMessage::Error(format!(
+                "Unable to remember the selected channel: {}",
+                e
+            )));
+        }
+
+        self.mention_candidates.clear();
+        self.transmit_to_dc(DiscordRequest::GetMentionCandidates(channel.id));
+
+        self.open_channel_tab(&channel);
+        self.tabs.select(TabId::Channel(channel.id));
+
+        if self
+            .active_channel
+            .as_ref()
+            .is_some_and(|active| active.id != channel.id)
+        {
+            self.selected_message = None;
+        }
+
+        self.active_channel = Some(channel);
+        self.load_history();
+    }
+
+    /// Updates what the bot can do in a joined channel, without switching to it.
+    fn refresh_channel(&mut self, channel: ChannelInfo) {
+        if let Some(known) = self.tab_channels.get_mut(&channel.id) {
+            known.permissions = channel.permissions;
+        }
+
+        if let Some(active) = &mut self.active_channel
+            && active.id == channel.id
+        {
+            active.permissions = channel.permissions;
+        }
+    }
+
+    fn open_channel_tab(&mut self, channel: &ChannelInfo) {
+        let title = match channel.name.is_empty() {
+            true => format!("#{}", channel.id),
+            false => format!("#{}", channel.name),
+        };
+
+        // Watched channels may have been opened under their ID
+        let tab = self.tabs.open(TabId::Channel(channel.id), &title);
+        tab.title = title;
+        self.tab_channels.insert(channel.id, channel.clone());
+    }
+
+    fn dm_conversation(&self, channel_id: u64) -> Option<&DirectConversation> {
+        self.dm_conversations
+            .iter()
+            .find(|c| c.channel_id == channel_id)
+    }
+
+    /// Shows the tab. Channel tabs also become the active channel, DM tabs send to their conversation.
+    fn select_tab(&mut self, id: TabId) {
+        self.tabs.select(id);
+
+        let TabId::Channel(channel_id) = id else {
+            return;
+        };
+
+        let active = self.active_channel.as_ref().map(|c| c.id);
+
+        if active == Some(channel_id) || self.dm_conversation(channel_id).is_some() {
+            return;
+        }
+
+        match self.tab_channels.get(&channel_id) {
+            Some(channel) => self.activate_channel(channel.clone()),
+            // Only watched so far, joining gets its name and permissions
+            None => self.transmit_to_dc(DiscordRequest::JoinChannel(channel_id)),
+        }
+    }
+
+    fn close_tab(&mut self, id: TabId) {
+        let TabId::Channel(channel_id) = id else {
+            return;
+        };
+
+        // It would reopen with the next message
+        if self
+            .active_channel
+            .as_ref()
+            .is_some_and(|c| c.id == channel_id)
+        {
+            self.add_message(GuiMessage::Error(
+                "You can't close the tab of the channel you're chatting in".to_string(),
+            ));
+            return;
+        }
+
+        self.tabs.close(id);
+        self.tab_channels.remove(&channel_id);
+    }
+
+    fn load_history(&mut self) {
+        let Some(channel) = &self.active_channel else {
+            return;
+        };
+
+        if !channel.permissions.read_history {
+            self.history_exhausted = true;
+            return;
+        }
+
+        self.history_loading = true;
+        self.history_exhausted = false;
+        self.transmit_to_dc(DiscordRequest::GetHistory(channel.id, None));
+    }
+
+    fn load_older_history(&mut self) {
+        if self.history_loading || self.history_exhausted {
+            return;
+        }
+
+        let Some(channel) = &self.active_channel else {
+            return;
+        };
+
+        let channel_id = channel.id;
+
+        let Some(tab) = self.tabs.get(TabId::Channel(channel_id)) else {
+            return;
+        };
+
+        let oldest = tab
+            .messages
+            .iter()
+            .filter_map(|msg| match msg {
+                GuiMessage::User(msg) if msg.channel_id == channel_id => Some(msg.id),
+                _ => None,
+            })
+            .min();
+
+        if let Some(oldest) = oldest {
+            self.history_loading = true;
+            self.transmit_to_dc(DiscordRequest::GetHistory(channel_id, Some(oldest)));
+        }
+    }
+
+    fn add_history(&mut self, channel_id: u64, history: Vec<ChatMessage>) {
+        if self.active_channel.as_ref().map(|c| c.id) != Some(channel_id) {
+            return;
+        }
+
+        self.history_loading = false;
+        self.history_exhausted = history.len() < discord::HISTORY_PAGE_SIZE as usize;
+
+        let history: Vec<GuiUserMessage> = history
+            .into_iter()
+            .filter(|msg| self.get_user_message(msg.channel_id, msg.id).is_none())
+            .map(|msg| self.user_message_from(msg))
+            .collect();
+
+        let Some(tab) = self.tabs.get_mut(TabId::Channel(channel_id)) else {
+            return;
+        };
+
+        // Live messages may already be there, IDs keep everything in order
+        for msg in history {
+            let idx = tab
+                .messages
+                .iter()
+                .position(|other| matches!(other, GuiMessage::User(other) if other.id > msg.id))
+                .unwrap_or(tab.messages.len());
+
+            tab.messages.insert(idx, GuiMessage::User(msg));
+        }
+    }
+
+    fn edit_message(&mut self, channel_id: u64, id: u64, content: String) {
+        if let Some(msg) = self.get_user_message_mut(channel_id, id) {
+            msg.content = match identity::parse_tag(&content) {
+                Some((_, content)) => content.to_string(),
+                None => content,
+            };
+            msg.edited = true;
+        }
+    }
+
+    fn update_embeds(&mut self, channel_id: u64, id: u64, embeds: Vec<EmbedInfo>) {
+        if let Some(msg) = self.get_user_message_mut(channel_id, id) {
+            msg.embeds = embeds;
+        }
+    }
+
+    fn update_reaction(&mut self, update: ReactionUpdate, added: bool) {
+        let Some(msg) = self.get_user_message_mut(update.channel_id, update.message_id) else {
+            return;
+        };
+
+        let existing = msg.reactions.iter_mut().find(|r| r.emoji == update.emoji);
+
+        match (existing, added) {
+            (Some(reaction), true) => {
+                reaction.count += 1;
+                reaction.me |= update.me;
+            }
+            (None, true) => msg.reactions.push(ReactionCount {
+                emoji: update.emoji,
+                count: 1,
+                me: update.me,
+            }),
+            (Some(reaction), false) => {
+                reaction.count = reaction.count.saturating_sub(1);
+
+                if update.me {
+                    reaction.me = false;
+                }
+            }
+            (None, false) => (),
+        }
+
+        msg.reactions.retain(|r| r.count > 0);
+    }
+
+    fn mark_messages_deleted(&mut self, channel_id: u64, ids: Vec<u64>) {
+        let Some(tab) = self.tabs.get_mut(TabId::Channel(channel_id)) else {
+            return;
+        };
+
+        for msg in &mut tab.messages {
+            if let GuiMessage::User(msg) = msg
+                && ids.contains(&msg.id)
+            {
+                msg.deleted = true;
+            }
+        }
+    }
+
+    fn get_user_message(&self, channel_id: u64, id: u64) -> Option<&GuiUserMessage> {
+        let tab = self.tabs.get(TabId::Channel(channel_id))?;
+
+        tab.messages.iter().find_map(|msg| match msg {
+            GuiMessage::User(msg) if msg.id == id => Some(msg),
+            _ => None,
+        })
+    }
+
+    fn get_user_message_mut(&mut self, channel_id: u64, id: u64) -> Option<&mut GuiUserMessage> {
+        let tab = self.tabs.get_mut(TabId::Channel(channel_id))?;
+
+        tab.messages.iter_mut().find_map(|msg| match msg {
+            GuiMessage::User(msg) if msg.id == id => Some(msg),
+            _ => None,
+        })
+    }
+
+    /// Selects or unselects a message of the selected tab.
+    fn toggle_selected_message(&mut self, idx: usize) {
+        if let GuiMessage::User(msg) = &self.tabs.selected().messages[idx] {
+            let key = (msg.channel_id, msg.id);
+
+            if self.selected_message == Some(key) {
+                self.selected_message = None;
+            } else {
+                self.selected_message = Some(key);
+            }
+        }
+    }
+
+    /// Moves the selection by `step` messages within the selected tab, starting from the latest one.
+    fn select_adjacent_message(&mut self, step: isize) {
+        let keys: Vec<(u64, u64)> = self
+            .tabs
+            .selected()
+            .messages
+            .iter()
+            .filter_map(|msg| match msg {
+                GuiMessage::User(msg) if !msg.deleted => Some((msg.channel_id, msg.id)),
+                _ => None,
+            })
+            .collect();
+
+        if keys.is_empty() {
+            return;
+        }
+
+        let current = self
+            .selected_message
+            .and_then(|key| keys.iter().position(|k| *k == key));
+
+        let new_idx = match current {
+            Some(idx) => idx as isize + step,
+            None if step < 0 => keys.len() as isize - 1,
+            None => return,
+        };
+
+        self.selected_message = if (0..keys.len() as isize).contains(&new_idx) {
+            Some(keys[new_idx as usize])
+        } else {
+            None
+        };
+    }
+
+    fn quote_preview(name: &str, content: &str) -> String {
+        let mut preview: String = content
+            .replace('\n', " ")
+            .chars()
+            .take(QUOTE_PREVIEW_LEN)
+            .collect();
+
+        if content.chars().count() > QUOTE_PREVIEW_LEN {
+            preview.push('…');
+        }
+
+        format!("{}: {}", name, preview)
+    }
+
+    fn user_message_from(&self, mut msg: ChatMessage) -> GuiUserMessage {
+        let mut name = msg.author_name.to_owned();
+        let mut name_color = None;
+        let mut via = None;
+
+        // Anyone could write a tag, only the ones Dove added when sending as this bot count
+        let own_id = self.own_user.as_ref().map(|user| user.id);
+
+        if own_id == Some(msg.author_id)
+            && let Some((tag, content)) = identity::parse_tag(&msg.content)
+        {
+            name = tag.name;
+            name_color = tag.color;
+            via = Some(msg.author_name.to_owned());
+            msg.content = content.to_string();
+        }
+
+        if let Some(quote) = &mut msg.reply_to
+            && own_id == Some(quote.author_id)
+            && let Some((tag, content)) = identity::parse_tag(&quote.content)
+        {
+            quote.author_name = tag.name;
+            quote.content = content.to_string();
+        }
+
+        let private = msg.guild_id.is_none();
+
+        let recipient = if private && self.is_own_message(&msg) {
+            self.dm_conversations
+                .iter()
+                .find(|c| c.channel_id == msg.channel_id)
+                .map(|c| c.user.name.to_owned())
+        } else {
+            None
+        };
+
+        GuiUserMessage {
+            id: msg.id,
+            channel_id: msg.channel_id,
+            name,
+            content: msg.content,
+            private,
+            recipient,
+            edited: msg.edited,
+            deleted: false,
+            reply_to: msg.reply_to,
+            reactions: msg.reactions,
+            attachments: msg.attachments,
+            embeds: msg.embeds,
+            mentions_me: msg.mentions_me,
+            from_webhook: msg.from_webhook,
+            name_color,
+            via,
+        }
+    }
+
+    fn channel_label(channel: &ChannelInfo) -> String {
+        if channel.name.is_empty() {
+            return format!("#{}", channel.id);
+        }
+
+        format!("#{} ({})", channel.name, channel.guild_name)
+    }
+
+    /// Adds command output or a notice to the system tab.
+    fn add_message(&mut self, msg: GuiMessage) {
+        self.tabs.system_mut().messages.push(msg);
+        self.tabs.mark_unread(TabId::System, false);
+    }
+
+    /// Adds a message to the tab of its channel, opening it if needed.
+    fn add_channel_message(&mut self, channel_id: u64, msg: GuiMessage) {
+        let title = match (
+            self.tab_channels.get(&channel_id),
+            self.dm_conversation(channel_id),
+        ) {
+            (Some(channel), _) if !channel.name.is_empty() => format!("#{}", channel.name),
+            (_, Some(conversation)) => format!("@{}", conversation.user.name),
+            _ => format!("#{}", channel_id),
+        };
+
+        self.tabs
+            .open(TabId::Channel(channel_id), &title)
+            .messages
+            .push(msg);
+    }
+
+    fn transmit_to_dc(&mut self, request: DiscordRequest) {
+        self.last_request_id += 1;
+        let id = self.last_request_id;
+
+        self.pending_requests.push(PendingRequest {
+            id,
+            request: request.clone(),
+            sent_at: Instant::now(),
+        });
+
+        // The Discord thread only stops if it panicked, nothing can be sent after that
+        if self.tx_to_dc.send(RequestEnvelope { id, request }).is_err() {
+            self.pending_requests.retain(|pending| pending.id != id);
+            self.connection_state = ConnectionState::Failed("Discord thread stopped".to_string());
+            self.connection_changed_at = Instant::now();
+            self.add_message(GuiMessage::Error(
+                "Lost contact with the Discord thread. Restart Dove to reconnect".to_string(),
+            ));
+        }
+    }
+
+    /// Requests the user doesn't wait for.
+    fn is_background_request(request: &DiscordRequest) -> bool {
+        matches!(
+            request,
+            DiscordRequest::TypingSend(_)
+                | DiscordRequest::GetMentionCandidates(_)
+                | DiscordRequest::SwitchAccount(_)
+                | DiscordRequest::OutboxFlush
+        )
+    }
+
+    fn finish_request(&mut self, id: u64, res: Result<DiscordResponse, ChatError>) {
+        // Already reported as timed out
+        let Some(idx) = self.pending_requests.iter().position(|p| p.id == id) else {
+            return;
+        };
+
+        let pending = self.pending_requests.remove(idx);
+
+        match res {
+            Ok(response) => self.handle_response(response),
+            Err(e) => self.handle_request_error(e, Some(pending.request)),
+        }
+    }
+
+    fn expire_requests(&mut self) {
+        let (expired, pending) = std::mem::take(&mut self.pending_requests)
+            .into_iter()
+            .partition(|p| p.sent_at.elapsed() >= REQUEST_TIMEOUT);
+
+        self.pending_requests = pending;
+
+        for request in expired {
+            let e = ChatError::Network("Discord didn't respond in time".to_string());
+            self.handle_request_error(e, Some(request.request));
+        }
+    }
+
+    /// Describes requests the user is waiting for.
+    fn pending_status(&self) -> Option<String> {
+        let count = self
+            .pending_requests
+            .iter()
+            .filter(|p| !Self::is_background_request(&p.request))
+            .count();
+
+        match count {
+            0 => None,
+            1 => Some("Waiting for Discord…".to_string()),
+            _ => Some(format!("Waiting for Discord… ({} requests)", count)),
+        }
+    }
+
+    fn clear_message(&mut self) {
+        self.text_to_send = String::new();
+    }
+
+    fn process_command(&mut self, input: String) {
+        let split: Vec<&str> = input.split_whitespace().collect();
+
+        if split.is_empty() {
+            return;
+        }
+
+        let alias = split[0];
+        let args: Vec<String> = split[1..].iter().map(|s| s.to_string()).collect();
+        let raw_args = input.trim_start()[alias.len()..].trim().to_string();
+
+        let cmd = self.get_command(alias.to_string());
+
+        if let Some(cmd) = cmd {
+            let ctx = CommandContext { args, raw_args };
+
+            cmd.execute(self, ctx);
+        } else {
+            self.add_message(GuiMessage::Error(format!("Unknown command '{}'", alias)));
+        }
+    }
+
+    fn submit_message(&mut self) {
+        let text = self.text_to_send.to_owned();
+
+        if text.trim().is_empty() {
+            return;
+        }
+
+        if text.starts_with(COMMAND_PREFIX) {
+            let cmd_text = &text[COMMAND_PREFIX.len()..];
+
+            self.clear_message();
+
+            let selected = self.tabs.selected().id;
+            let output_len = Self::system_output_len(&self.tabs);
+
+            self.process_command(cmd_text.to_string());
+
+            // Show the output, unless the command switched tabs itself
+            if self.tabs.selected().id == selected
+                && Self::system_output_len(&self.tabs) != output_len
+            {
+                self.select_tab(TabId::System);
+            }
+
+            return;
+        }
+
+        if self.token_regex.is_match(&text) {
+            self.add_message(GuiMessage::Error(
+                "Your message was not sent, because it possibly contained Discord token."
+                    .to_string(),
+            ));
+            return;
+        }
+
+        if let TabId::Channel(channel_id) = self.tabs.selected().id
+            && let Some(conversation) = self.dm_conversation(channel_id)
+        {
+            let user_id = conversation.user.id;
+
+            self.send_direct_message(user_id.to_string(), text);
+            self.clear_message();
+            return;
+        }
+
+        if self.send_to_active_channel(text, Vec::new()) {
+            self.clear_message();
+        }
+    }
+
+    fn system_output_len(tabs: &Tabs<GuiMessage>) -> usize {
+        tabs.get(TabId::System)
+            .map(|tab| tab.messages.len())
+            .unwrap_or_default()
+    }
+
+    fn find_outgoing(&self, channel_id: u64, entry_id: u64) -> Option<usize> {
+        self.tabs
+            .get(TabId::Channel(channel_id))?
+            .messages
+            .iter()
+            .position(|msg| matches!(msg, GuiMessage::Outgoing(echo) if echo.entry.id == entry_id))
+    }
+
+    /// Finds the echo of an own message that was sent as the message with the ID.
+    fn find_outgoing_echo(&self, channel_id: u64, message_id: u64) -> Option<usize> {
+        self.tabs
+            .get(TabId::Channel(channel_id))?
+            .messages
+            .iter()
+            .position(|msg| {
+                matches!(msg, GuiMessage::Outgoing(echo) if echo.entry.status == OutboxStatus::Sent(message_id))
+            })
+    }
+
+    fn update_outgoing(&mut self, entry: OutboxEntry) {
+        let channel_id = entry.message.channel_id;
+        let idx = self.find_outgoing(channel_id, entry.id);
+
+        // The real message may arrive before the confirmation
+        if let OutboxStatus::Sent(message_id) = entry.status
+            && self.get_user_message(channel_id, message_id).is_some()
+        {
+            if let Some(idx) = idx
+                && let Some(tab) = self.tabs.get_mut(TabId::Channel(channel_id))
+            {
+                tab.messages.remove(idx);
+            }
+            return;
+        }
+
+        match idx {
+            Some(idx) => {
+                if let Some(tab) = self.tabs.get_mut(TabId::Channel(channel_id))
+                    && let GuiMessage::Outgoing(echo) = &mut tab.messages[idx]
+                {
+                    echo.entry = entry;
+                }
+            }
+            None => {
+                let tag = identity::parse_tag(&entry.message.content).map(|(tag, _)| tag);
+                let name = match (&entry.message.webhook, tag, &self.own_user) {
+                    (Some(identity), _, _) => identity.name.to_owned(),
+                    (None, Some(tag), _) => tag.name,
+                    (None, None, Some(user)) => user.name.to_owned(),
+                    (None, None, None) => "You".to_string(),
+                };
+
+                let echo = GuiMessage::Outgoing(GuiOutgoingMessage { name, entry });
+                self.add_channel_message(channel_id, echo);
+            }
+        }
+    }
+
+    /// Shows why a request failed and what the user can do about it.
+    fn handle_request_error(&mut self, error: ChatError, request: Option<DiscordRequest>) {
+        if let Some(request) = &request {
+            if Self::is_background_request(request) {
+                return;
+            }
+
+            if matches!(request, DiscordRequest::GetHistory(..)) {
+                self.history_loading = false;
+            }
+        }
+
+        let hint = match &error {
+            ChatError::Auth(_) => Some("Use /login <token> to log in"),
+            ChatError::Network(_) => Some("Check your internet connection and try again"),
+            ChatError::InvalidInput(_) => Some("Check what you entered and try again"),
+            _ => None,
+        };
+
+        let text = match hint {
+            Some(hint) => format!("{}. {}", error, hint),
+            None => error.to_string(),
+        };
+
+        self.add_message(GuiMessage::Error(text));
+    }
+
+    fn set_connection_state(&mut self, state: ConnectionState) {
+        match &state {
+            ConnectionState::Ready
+                if matches!(self.connection_state, ConnectionState::Reconnecting { .. }) =>
+            {
+                self.add_message(GuiMessage::Generic("Reconnected".to_string()));
+            }
+            ConnectionState::Reconnecting {
+                attempt: 1, reason, ..
+            } => {
+                self.add_message(GuiMessage::Error(format!(
+                    "Connection lost: {}. Reconnecting…",
+                    reason
+                )));
+            }
+            ConnectionState::Failed(reason) => {
+                self.own_user = None;
+                self.add_message(GuiMessage::Error(format!(
+                    "Disconnected: {}. Use /login to try again",
+                    reason
+                )));
+            }
+            _ => (),
+        }
+
+        self.connection_state = state;
+        self.connection_changed_at = Instant::now();
+    }
+
+    /// Refuses the same text sent many times in a row, usually by accident.
+    fn is_flood(&mut self, content: &str) -> bool {
+        // Files are sent without text
+        if content.is_empty() || self.flood_guard.check(content, Instant::now()) {
+            return false;
+        }
+
+        self.add_message(GuiMessage::Error(format!(
+            "Not sent, you already sent this message {} times in the last {}s",
+            ratelimit::FLOOD_REPEATS,
+            ratelimit::FLOOD_WINDOW.as_secs()
+        )));
+
+        true
+    }
+
+    fn delay_sending(&mut self, channel_id: Option<u64>, delay: Duration) {
+        let until = Instant::now() + delay;
+        let current = self.send_delays.entry(channel_id).or_insert(until);
+
+        *current = until.max(*current);
+    }
+
+    /// Sends the messages that waited once a delay is over.
+    fn expire_send_delays(&mut self) {
+        let now = Instant::now();
+        let count = self.send_delays.len();
+
+        self.send_delays.retain(|_, until| *until > now);
+
+        if self.send_delays.len() < count && self.connection_state == ConnectionState::Ready {
+            self.transmit_to_dc(DiscordRequest::OutboxFlush);
+        }
+    }
+
+    /// Counts down until messages can be sent to the channel of the selected tab.
+    fn send_delay_status(&self) -> Option<String> {
+        let channel_id = match self.tabs.selected().id {
+            TabId::Channel(channel_id) => channel_id,
+            TabId::System => self.active_channel.as_ref()?.id,
+        };
+
+        let until = [
+            self.send_delays.get(&Some(channel_id)),
+            self.send_delays.get(&None),
+        ]
+        .into_iter()
+        .flatten()
+        .max()?;
+
+        let remaining = until.checked_duration_since(Instant::now())?;
+
+        Some(format!(
+            "Slowmode or rate limit, messages are sent in {}s",
+            remaining.as_secs() + 1
+        ))
+    }
+
+    /// Describes the connection while it isn't usable.
+    fn connection_status(&self) -> Option<String> {
+        match &self.connection_state {
+            ConnectionState::Connecting => Some("Connecting…".to_string()),
+            ConnectionState::Reconnecting { attempt, delay, .. } => {
+                let remaining = delay.saturating_sub(self.connection_changed_at.elapsed());
+
+                if remaining.is_zero() {
+                    Some(format!("Reconnecting… (attempt {})", attempt))
+                } else {
+                    Some(format!(
+                        "Reconnecting in {}s (attempt {})",
+                        remaining.as_secs() + 1,
+                        attempt
+                    ))
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Lets the active channel know the user is composing, at most once per typing window.
+    fn broadcast_typing(&mut self) {
+        let text = &self.text_to_send;
+
+        if text.trim().is_empty() || text.starts_with(COMMAND_PREFIX) {
+            return;
+        }
+
+        let Some(channel) = &self.active_channel else {
+            return;
+        };
+
+        if !channel.permissions.send {
+            return;
+        }
+
+        if let Some((channel_id, sent_at)) = self.last_typing_sent
+            && channel_id == channel.id
+            && sent_at.elapsed() < discord::TYPING_DURATION
+        {
+            return;
+        }
+
+        let channel_id = channel.id;
+        self.last_typing_sent = Some((channel_id, Instant::now()));
+        self.transmit_to_dc(DiscordRequest::TypingSend(channel_id));
+    }
+
+    fn start_typing(&mut self, update: TypingUpdate) {
+        self.stop_typing(update.channel_id, update.user.id);
+        self.typing_users.push((update, Instant::now()));
+    }
+
+    fn stop_typing(&mut self, channel_id: u64, user_id: u64) {
+        self.typing_users
+            .retain(|(t, _)| !(t.channel_id == channel_id && t.user.id == user_id));
+    }
+
+    /// Returns names of users typing in the active channel.
+    fn get_typing_names(&mut self) -> Vec<String> {
+        self.typing_users
+            .retain(|(_, started)| started.elapsed() < discord::TYPING_DURATION);
+
+        let Some(channel) = &self.active_channel else {
+            return Vec::new();
+        };
+
+        self.typing_users
+            .iter()
+            .filter(|(t, _)| t.channel_id == channel.id)
+            .map(|(t, _)| t.user.name.to_owned())
+            .collect()
+    }
+
+    fn typing_label(names: &[String]) -> String {
+        match names {
+            [name] => format!("{} is typing…", name),
+            [first, second] => format!("{} and {} are typing…", first, second),
+            _ => "Several people are typing…".to_string(),
+        }
+    }
+
+    /// Shows members or channels matching the mention being typed.
+    /// Up/Down picks one, Tab, Enter or a click returns it for insertion.
+    fn add_mention_suggestions(&mut self, ui: &mut Ui) -> Option<MentionCandidate> {
+        let suggestions: Vec<MentionCandidate> = match CompletionQuery::find(&self.text_to_send) {
+            Some(query) => query
+                .filter(&self.mention_candidates)
+                .into_iter()
+                .cloned()
+                .collect(),
+            None => Vec::new(),
+        };
+
+        if suggestions.is_empty() {
+            self.selected_suggestion = 0;
+            return None;
+        }
+
+        let count = suggestions.len();
+        let mut selected = self.selected_suggestion.min(count - 1);
+        let mut accepted = None;
+
+        // Consumed before the input sees them, so the cursor stays in place
+        if ui.input_mut(|inp| inp.consume_key(Modifiers::NONE, Key::ArrowDown)) {
+            selected = (selected + 1) % count;
+        }
+
+        if ui.input_mut(|inp| inp.consume_key(Modifiers::NONE, Key::ArrowUp)) {
+            selected = (selected + count - 1) % count;
+        }
+
+        if ui.input_mut(|inp| {
+            inp.consume_key(Modifiers::NONE, Key::Tab)
+                || inp.consume_key(Modifiers::NONE, Key::Enter)
+        }) {
+            accepted = Some(selected);
+        }
+
+        for (i, candidate) in suggestions.iter().enumerate() {
+            let prefix = match candidate.mention {
+                Mention::Channel(_) => "#",
+                _ => "@",
+            };
+
+            let text = RichText::new(format!("{}{}", prefix, candidate.name)).small();
+
+            if ui.selectable_label(i == selected, text).clicked() {
+                accepted = Some(i);
+            }
+        }
+
+        self.selected_suggestion = selected;
+
+        accepted.map(|i| suggestions[i].clone())
+    }
+
+    fn set_mention_candidates(&mut self, channel_id: u64, candidates: Vec<MentionCandidate>) {
+        // Ignore lists that arrive after switching channels
+        if self.active_channel.as_ref().map(|c| c.id) == Some(channel_id) {
+            self.mention_candidates = candidates;
+        }
+    }
+
+    fn insert_mention(&mut self, candidate: &MentionCandidate) {
+        if let Some(query) = CompletionQuery::find(&self.text_to_send) {
+            self.text_to_send = query.apply(&self.text_to_send, candidate);
+        }
+
+        self.selected_suggestion = 0;
+    }
+
+    /// Returns how many messages the draft will be split into.
+    fn count_message_parts(&self) -> usize {
+        if self.text_to_send.starts_with(COMMAND_PREFIX) {
+            return 0;
+        }
+
+        let tagged = self.active_channel.as_ref().is_none_or(|channel| {
+            self.webhooks
+                .identity_for(channel.id, &self.identity)
+                .is_none()
+        });
+
+        self.outgoing_parts(&self.text_to_send, tagged).len()
+    }
+
+    /// Splits a message to fit Discord's limit, starting each part with the sender tag
+    /// unless it's sent through a webhook, which already shows the nickname.
+    fn outgoing_parts(&self, content: &str, tagged: bool) -> Vec<String> {
+        let tag = match self.identity.tag() {
+            Some(tag) if tagged => tag,
+            _ => String::new(),
+        };
+
+        utils::text::split_message(content, discord::MESSAGE_LEN_LIMIT - tag.chars().count())
+            .into_iter()
+            .map(|part| format!("{}{}", tag, part))
+            .collect()
+    }
+
+    /// Returns false if the message could not be sent.
+    fn send_to_active_channel(&mut self, content: String, mut attachments: Vec<PathBuf>) -> bool {
+        let Some(channel) = &self.active_channel else {
+            self.add_message(GuiMessage::Error(
+                "No channel selected. Use /join <channel-id> or /join <server> #<channel>"
+                    .to_string(),
+            ));
+            return false;
+        };
+
+        if !channel.permissions.send {
+            self.add_message(GuiMessage::Error(format!(
+                "You can't send messages in {}, the bot is missing the Send Messages permission",
+                Self::channel_label(channel)
+            )));
+            return false;
+        }
+
+        if !attachments.is_empty() && !channel.permissions.attach {
+            self.add_message(GuiMessage::Error(format!(
+                "You can't upload files to {}, the bot is missing the Attach Files permission",
+                Self::channel_label(channel)
+            )));
+            return false;
+        }
+
+        let mut reply_to = match self.selected_message {
+            Some((channel_id, id)) if channel_id == channel.id => Some(id),
+            _ => None,
+        };
+
+        // Webhooks can't reply, so replies are sent by the bot
+        let webhook = match reply_to {
+            Some(_) => None,
+            None => self.webhooks.identity_for(channel.id, &self.identity),
+        };
+
+        let channel_id = channel.id;
+
+        if self.is_flood(&content) {
+            return false;
+        }
+
+        let mut parts = self.outgoing_parts(&content, webhook.is_none());
+
+        // Files can be sent without text, but still name the sender
+        if parts.is_empty() {
+            let tag = match &webhook {
+                Some(_) => None,
+                None => self.identity.tag(),
+            };
+            parts.push(tag.unwrap_or_default().trim_end().to_string());
+        }
+
+        // Only the first part replies and carries the files
+        for part in parts {
+            self.transmit_to_dc(DiscordRequest::MessageSend(OutgoingMessage {
+                channel_id,
+                content: part,
+                reply_to: reply_to.take(),
+                attachments: std::mem::take(&mut attachments),
+                webhook: webhook.clone(),
+            }));
+        }
+
+        self.selected_message = None;
+        // Discord clears the indicator once the message arrives
+        self.last_typing_sent = None;
+
+        true
+    }
+
+    fn save_attachment(&mut self, attachment: AttachmentInfo, data: Vec<u8>) {
+        match config::save_download(attachment.id, &attachment.filename, &data) {
+            Ok(path) => {
+                self.add_message(GuiMessage::Generic(format!("Saved to {}", path.display())));
+            }
+            Err(e) => {
+                self.add_message(GuiMessage::Error(format!(
+                    "Unable to save '{}': {}",
+                    attachment.filename, e
+                )));
+            }
+        }
+    }
+
+    fn poll_global_hotkeys(&mut self) -> bool {
+        if let Ok(event) = self.global_key_receiver.try_recv() {
+            if event.id == self.open_chat_hotkey.id {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    fn handle_response(&mut self, response: DiscordResponse) {
+        match response {
+            DiscordResponse::MentionCandidatesListed(channel_id, candidates) => {
+                self.set_mention_candidates(channel_id, candidates);
+            }
+            DiscordResponse::DirectMessageSent(conversation) => {
+                self.track_dm_conversation(conversation);
+            }
+            DiscordResponse::GuildsListed(guilds) => {
+                self.add_message(GuiMessage::Generic("Available servers:".to_string()));
+
+                for guild in &guilds {
+                    self.add_message(GuiMessage::Generic(format!(
+                        " {}: {}",
+                        guild.id, guild.name
+                    )));
+                }
+            }
+            DiscordResponse::AvailableTextChannelsListed(channels) => {
+                self.list_channels(channels);
+            }
+            DiscordResponse::ChannelJoined(channel) => {
+                self.set_active_channel(channel);
+            }
+            DiscordResponse::ChannelRefreshed(channel) => {
+                self.refresh_channel(channel);
+            }
+            DiscordResponse::HistoryLoaded(channel_id, history) => {
+                self.add_history(channel_id, history);
+            }
+            DiscordResponse::AttachmentDownloaded(attachment, data) => {
+                self.save_attachment(attachment, data);
+            }
+            DiscordResponse::Done => (),
+        }
+    }
+
+    fn poll_discord_events(&mut self) {
+        match self.rx_from_dc.try_recv() {
+            Ok(event) => match event {
+                DiscordCommEvent::Ready(user) => {
+                    // Reconnecting also ends with Ready
+                    let reconnected = self.own_user.is_some();
+
+                    if !reconnected {
+                        self.add_message(GuiMessage::Generic("Logged in successfully".to_string()));
+                    }
+
+                    self.own_user = Some(user);
+
+                    if let Some(token) = self.token_to_save.take() {
+                        match config::save_token(&self.accounts.last_used, token) {
                             Ok(()) => {
                                 self.add_message(GuiMessage::Generic(
                                     "Your token was encrypted and saved".to_string(),
@@ -335,90 +2362,525 @@ impl App {
                             }
                         }
                     }
+
+                    self.transmit_to_dc(DiscordRequest::OutboxFlush);
+
+                    // Permissions remembered from the last session or connection may have changed
+                    if let Some(channel) = &self.active_channel {
+                        let channel_id = channel.id;
+                        self.transmit_to_dc(DiscordRequest::RefreshChannel(channel_id));
+
+                        if !reconnected {
+                            self.transmit_to_dc(DiscordRequest::GetMentionCandidates(channel_id));
+                            self.load_history();
+                        }
+                    }
+                }
+                DiscordCommEvent::Responded(id, res) => {
+                    self.finish_request(id, res);
                 }
-                DiscordCommEvent::Error(text) => {
-                    self.add_message(GuiMessage::Error(text));
+                DiscordCommEvent::Error(error) => {
+                    self.handle_request_error(error, None);
                 }
                 DiscordCommEvent::MessageReceived(msg) => {
-                    let name = msg
-                        .author
-                        .display_name()
-                        .replace("[dove]", "")
-                        .trim()
-                        .to_string();
-
-                    let msg_struct = GuiUserMessage {
-                        name: name,
-                        content: msg.content,
-                        private: msg.guild_id.is_none(),
-                    };
+                    self.stop_typing(msg.channel_id, msg.author_id);
 
-                    self.add_message(GuiMessage::User(msg_struct));
-                }
-                DiscordCommEvent::GuildsListed(guilds) => {
-                    self.add_message(GuiMessage::Generic("Available servers:".to_string()));
+                    if !self.is_subscribed(&msg) {
+                        return;
+                    }
 
-                    for guild in &guilds {
-                        self.add_message(GuiMessage::Generic(format!(
-                            " {}: {}",
-                            guild.id, guild.name
-                        )));
+                    if msg.guild_id.is_none() && !self.is_own_message(&msg) {
+                        self.last_dm_sender = Some(msg.author_id);
+                        self.track_dm_conversation(DirectConversation {
+                            channel_id: msg.channel_id,
+                            user: UserSummary {
+                                id: msg.author_id,
+                                name: msg.author_name.to_owned(),
+                            },
+                        });
                     }
-                }
-                DiscordCommEvent::AvailableTextChannelsListed(channels) => {
-                    self.add_message(GuiMessage::Generic("Available channels:".to_string()));
 
-                    for channel in channels {
-                        self.add_message(GuiMessage::Generic(format!(
-                            " {}: #{}",
-                            channel.id, channel.name
-                        )));
+                    let channel_id = msg.channel_id;
+                    let echo_idx = self.find_outgoing_echo(channel_id, msg.id);
+                    let own = self.is_own_message(&msg);
+                    let user_msg = self.user_message_from(msg);
+                    // Direct messages are meant for the user just like mentions
+                    let mention = user_msg.mentions_me || user_msg.private;
+                    let user_msg = GuiMessage::User(user_msg);
+
+                    match (echo_idx, self.tabs.get_mut(TabId::Channel(channel_id))) {
+                        (Some(idx), Some(tab)) => tab.messages[idx] = user_msg,
+                        _ => {
+                            self.add_channel_message(channel_id, user_msg);
+
+                            if !own {
+                                self.tabs.mark_unread(TabId::Channel(channel_id), mention);
+                            }
+                        }
+                    }
+                }
+                DiscordCommEvent::TypingStarted(update) => {
+                    self.start_typing(update);
+                }
+                DiscordCommEvent::OutboxUpdated(entry) => {
+                    self.update_outgoing(entry);
+                }
+                DiscordCommEvent::SendDelayed(channel_id, delay) => {
+                    self.delay_sending(channel_id, delay);
+                }
+                DiscordCommEvent::OutboxDiscarded(id) => {
+                    for tab in self.tabs.iter_mut() {
+                        tab.messages.retain(
+                            |msg| !matches!(msg, GuiMessage::Outgoing(echo) if echo.entry.id == id),
+                        );
                     }
                 }
-                _ => (),
+                DiscordCommEvent::ConnectionStateChanged(state) => {
+                    self.set_connection_state(state);
+                }
+                DiscordCommEvent::MessageEdited(channel_id, id, content) => {
+                    self.edit_message(channel_id, id, content);
+                }
+                DiscordCommEvent::EmbedsUpdated(channel_id, id, embeds) => {
+                    self.update_embeds(channel_id, id, embeds);
+                }
+                DiscordCommEvent::MessagesDeleted(channel_id, ids) => {
+                    self.mark_messages_deleted(channel_id, ids);
+                }
+                DiscordCommEvent::ReactionAdded(update) => {
+                    self.update_reaction(update, true);
+                }
+                DiscordCommEvent::ReactionRemoved(update) => {
+                    self.update_reaction(update, false);
+                }
             },
             Err(_) => (),
         }
     }
 
-    fn add_label_for_message(ui: &mut Ui, message: &GuiMessage) {
+    /// Returns the clicked tab, and whether it was middle-clicked to close it.
+    fn add_tab_bar(&self, ui: &mut Ui) -> Option<(TabId, bool)> {
+        let selected = self.tabs.selected().id;
+        let mut clicked = None;
+
+        ui.horizontal_wrapped(|ui| {
+            for tab in self.tabs.iter() {
+                let mut texts = vec![RichText::new(&tab.title)];
+
+                if tab.mentions > 0 {
+                    texts.push(
+                        RichText::new(format!(" @{}", tab.mentions))
+                            .color(Color32::from_rgb(250, 168, 26))
+                            .strong(),
+                    );
+                } else if tab.unread > 0 {
+                    texts.push(RichText::new(format!(" {}", tab.unread)).color(Color32::GRAY));
+                }
+
+                let mut resp =
+                    ui.selectable_label(tab.id == selected, utils::ui::combine_rich_text(texts));
+
+                if tab.id != TabId::System {
+                    resp = resp.on_hover_text("Middle-click to close");
+                }
+
+                if resp.clicked() {
+                    clicked = Some((tab.id, false));
+                } else if resp.middle_clicked() {
+                    clicked = Some((tab.id, true));
+                }
+            }
+        });
+
+        clicked
+    }
+
+    fn add_reactions_label(ui: &mut Ui, reactions: &[ReactionCount]) {
+        let texts: Vec<RichText> = reactions
+            .iter()
+            .map(|reaction| {
+                let text = format!(
+                    " {} {} ",
+                    discord::emoji_label(&reaction.emoji),
+                    reaction.count
+                );
+                let color = if reaction.me {
+                    Color32::from_rgb(116, 113, 255)
+                } else {
+                    Color32::GRAY
+                };
+
+                RichText::new(text).color(color).small()
+            })
+            .collect();
+
+        ui.label(utils::ui::combine_rich_text(texts));
+    }
+
+    fn add_embed(ui: &mut Ui, embed: &EmbedInfo) {
+        let bar_color = match embed.color {
+            Some(color) if color != 0 => utils::ui::color_from_rgb(color),
+            _ => Color32::DARK_GRAY,
+        };
+
+        let frame = Frame::new()
+            .fill(Color32::from_black_alpha(80))
+            .inner_margin(egui::Margin {
+                left: 8,
+                right: 4,
+                top: 2,
+                bottom: 2,
+            });
+
+        let resp = frame.show(ui, |ui| {
+            if let Some(author) = &embed.author {
+                ui.label(RichText::new(author).color(Color32::WHITE).small());
+            }
+
+            if let Some(title) = &embed.title {
+                let title = RichText::new(title).strong();
+
+                match &embed.url {
+                    Some(url) => ui.hyperlink_to(title, url),
+                    None => ui.label(title.color(Color32::WHITE)),
+                };
+            }
+
+            if let Some(description) = &embed.description {
+                ui.label(RichText::new(description).color(Color32::WHITE));
+            }
+
+            for (name, value) in &embed.fields {
+                ui.label(RichText::new(name).color(Color32::WHITE).strong());
+                ui.label(RichText::new(value).color(Color32::WHITE));
+            }
+
+            if let Some(footer) = &embed.footer {
+                ui.label(RichText::new(footer).color(Color32::GRAY).small());
+            }
+        });
+
+        let rect = resp.response.rect;
+        ui.painter().vline(
+            rect.left() + 1.5,
+            rect.y_range(),
+            egui::Stroke::new(3.0, bar_color),
+        );
+    }
+
+    /// Returns the clicked attachment, if any.
+    fn add_attachment_labels(
+        ui: &mut Ui,
+        attachments: &[AttachmentInfo],
+    ) -> Option<AttachmentInfo> {
+        let mut clicked: Option<AttachmentInfo> = None;
+
+        for attachment in attachments {
+            let text = format!(
+                "📎 {} ({})",
+                attachment.filename,
+                utils::ui::format_size(attachment.size)
+            );
+            let label = Label::new(RichText::new(text).color(Color32::LIGHT_BLUE).small())
+                .sense(Sense::click());
+
+            if ui
+                .add(label)
+                .on_hover_text("Click to save to the downloads folder")
+                .clicked()
+            {
+                clicked = Some(attachment.clone());
+            }
+        }
+
+        clicked
+    }
+
+    fn add_label_for_message(
+        ui: &mut Ui,
+        message: &GuiMessage,
+        selected: bool,
+    ) -> Option<MessageAction> {
         match message {
             GuiMessage::Generic(text) => {
                 ui.label(text);
+                None
             }
             GuiMessage::User(msg) => {
+                if let Some(quote) = &msg.reply_to {
+                    let preview = Self::quote_preview(&quote.author_name, &quote.content);
+
+                    ui.label(
+                        RichText::new(format!("╭ {}", preview))
+                            .color(Color32::GRAY)
+                            .small(),
+                    );
+                }
+
                 let mut texts: Vec<RichText> = Vec::new();
 
                 if msg.private {
+                    let prefix = match &msg.recipient {
+                        Some(recipient) => format!("[To {}] ", recipient),
+                        None => format!("[From {}] ", &msg.name),
+                    };
+
+                    texts.push(RichText::new(prefix).color(Color32::WHITE));
+                }
+
+                let color = msg
+                    .name_color
+                    .map(utils::ui::color_from_rgb)
+                    .unwrap_or(Color32::from_rgb(116, 113, 255));
+
+                texts.push(RichText::new(&msg.name).color(color).strong());
+
+                if let Some(via) = &msg.via {
                     texts.push(
-                        RichText::new(format!("[From {}] ", &msg.name)).color(Color32::WHITE),
+                        RichText::new(format!(" via {}", via))
+                            .color(Color32::GRAY)
+                            .small(),
                     );
                 }
 
-                let color = Color32::from_rgb(116, 113, 255);
+                if msg.from_webhook {
+                    texts.push(RichText::new(" [webhook]").color(Color32::GRAY).small());
+                }
 
-                texts.push(RichText::new(&msg.name).color(color).strong());
-                texts.push(RichText::new(format!(": {}", &msg.content)).color(Color32::WHITE));
+                // Embed-only messages (bots, link previews) have no text
+                let content_text = if msg.content.is_empty() {
+                    String::new()
+                } else {
+                    format!(": {}", &msg.content)
+                };
+
+                let mut content = RichText::new(content_text).color(Color32::WHITE);
+
+                if msg.deleted {
+                    content = content.strikethrough().color(Color32::GRAY);
+                }
+
+                texts.push(content);
+
+                if msg.deleted {
+                    texts.push(RichText::new(" (deleted)").color(Color32::GRAY).small());
+                } else if msg.edited {
+                    texts.push(RichText::new(" (edited)").color(Color32::GRAY).small());
+                }
+
+                let background = if selected {
+                    Some(Color32::from_white_alpha(24))
+                } else if msg.mentions_me {
+                    Some(Color32::from_rgba_unmultiplied(250, 168, 26, 40))
+                } else {
+                    None
+                };
+
+                if let Some(background) = background {
+                    texts = texts
+                        .into_iter()
+                        .map(|text| text.background_color(background))
+                        .collect();
+                }
+
+                let label = Label::new(utils::ui::combine_rich_text(texts)).sense(Sense::click());
+                let resp = ui.add(label);
+
+                for embed in &msg.embeds {
+                    Self::add_embed(ui, embed);
+                }
 
-                ui.label(utils::ui::combine_rich_text(texts));
+                let downloaded = Self::add_attachment_labels(ui, &msg.attachments);
+
+                if !msg.reactions.is_empty() {
+                    Self::add_reactions_label(ui, &msg.reactions);
+                }
+
+                if let Some(attachment) = downloaded {
+                    Some(MessageAction::Download(attachment))
+                } else if resp.clicked() {
+                    Some(MessageAction::Select)
+                } else {
+                    None
+                }
             }
+            GuiMessage::Outgoing(echo) => Self::add_outgoing_label(ui, echo),
             GuiMessage::Error(text) => {
                 ui.label(RichText::new(text).color(Color32::RED));
+                None
             }
+        }
+    }
+
+    fn add_outgoing_label(ui: &mut Ui, echo: &GuiOutgoingMessage) -> Option<MessageAction> {
+        let msg = &echo.entry.message;
+        let content = match identity::parse_tag(&msg.content) {
+            Some((_, content)) => content,
+            None => &msg.content,
         };
+
+        let mut texts = vec![
+            RichText::new(&echo.name).color(Color32::GRAY).strong(),
+            RichText::new(format!(": {}", content)).color(Color32::GRAY),
+        ];
+
+        for path in &msg.attachments {
+            let filename = path.file_name().unwrap_or_default().to_string_lossy();
+            texts.push(RichText::new(format!(" 📎 {}", filename)).color(Color32::GRAY));
+        }
+
+        match &echo.entry.status {
+            OutboxStatus::Pending => {
+                texts.push(RichText::new(" (sending…)").color(Color32::GRAY).small());
+            }
+            OutboxStatus::Failed(reason) => {
+                texts.push(
+                    RichText::new(format!(" (not sent: {})", reason))
+                        .color(Color32::RED)
+                        .small(),
+                );
+            }
+            OutboxStatus::Sent(_) => (),
+        }
+
+        ui.label(utils::ui::combine_rich_text(texts));
+
+        if !matches!(echo.entry.status, OutboxStatus::Failed(_)) {
+            return None;
+        }
+
+        let mut action = None;
+
+        ui.horizontal(|ui| {
+            let retry = Label::new(RichText::new("Retry").color(Color32::LIGHT_BLUE).small())
+                .sense(Sense::click());
+            let discard = Label::new(RichText::new("Discard").color(Color32::LIGHT_BLUE).small())
+                .sense(Sense::click());
+
+            if ui.add(retry).clicked() {
+                action = Some(MessageAction::Retry(echo.entry.id));
+            }
+
+            if ui.add(discard).clicked() {
+                action = Some(MessageAction::Discard(echo.entry.id));
+            }
+        });
+
+        action
     }
 }
 
 impl eframe::App for App {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         self.poll_discord_events();
+        self.expire_requests();
+        self.expire_send_delays();
 
         egui::TopBottomPanel::bottom("bottom").show(ctx, |ui| {
+            let dm_recipient = match self.tabs.selected().id {
+                TabId::Channel(channel_id) => self
+                    .dm_conversation(channel_id)
+                    .map(|c| c.user.name.to_owned()),
+                TabId::System => None,
+            };
+
+            let hint = match &self.active_channel {
+                _ if dm_recipient.is_some() => {
+                    format!("Message @{}", dm_recipient.unwrap_or_default())
+                }
+                Some(channel) if !channel.permissions.send => {
+                    format!("{} is read-only", Self::channel_label(channel))
+                }
+                Some(channel) => match self.webhooks.identity_for(channel.id, &self.identity) {
+                    Some(identity) => format!(
+                        "Message {} as {}",
+                        Self::channel_label(channel),
+                        identity.name
+                    ),
+                    None => format!("Message {}", Self::channel_label(channel)),
+                },
+                None => "No channel selected, use /join".to_string(),
+            };
+
+            if let Some(status) = self.connection_status() {
+                ui.label(RichText::new(status).color(Color32::YELLOW).small());
+
+                // Keep the countdown running
+                ctx.request_repaint_after(Duration::from_secs(1));
+            }
+
+            if let Some(status) = self.send_delay_status() {
+                ui.label(RichText::new(status).color(Color32::YELLOW).small());
+                ctx.request_repaint_after(Duration::from_secs(1));
+            }
+
+            if let Some(status) = self.pending_status() {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label(RichText::new(status).color(Color32::GRAY).small());
+                });
+            }
+
+            let typing_names = self.get_typing_names();
+
+            if !typing_names.is_empty() {
+                ui.label(
+                    RichText::new(Self::typing_label(&typing_names))
+                        .color(Color32::GRAY)
+                        .small(),
+                );
+
+                // Hide the indicator once it expires even if nothing else happens
+                ctx.request_repaint_after(Duration::from_secs(1));
+            }
+
+            if let Some((channel_id, id)) = self.selected_message {
+                let preview = match self.get_user_message(channel_id, id) {
+                    Some(msg) => Self::quote_preview(&msg.name, &msg.content),
+                    None => "message".to_string(),
+                };
+
+                ui.label(
+                    RichText::new(format!("Replying to {} (Esc to cancel)", preview))
+                        .color(Color32::GRAY)
+                        .small(),
+                );
+            }
+
+            let suggestion = self.add_mention_suggestions(ui);
+
+            let part_count = self.count_message_parts();
+
+            if part_count > 1 {
+                ui.label(
+                    RichText::new(format!("Will be sent as {} messages", part_count))
+                        .color(Color32::GRAY)
+                        .small(),
+                );
+            }
+
             let msg_input = TextEdit::singleline(&mut self.text_to_send)
-                .hint_text("Type your message...")
-                .char_limit(discord::MESSAGE_LEN_LIMIT);
+                .hint_text(hint)
+                // Tab accepts mention suggestions
+                .lock_focus(true);
             let msg_input_resp = ui.add_sized(ui.available_size(), msg_input);
 
+            if let Some(candidate) = suggestion {
+                self.insert_mention(&candidate);
+
+                if let Some(mut state) = TextEdit::load_state(ctx, msg_input_resp.id) {
+                    let end = CCursor::new(self.text_to_send.chars().count());
+                    state.cursor.set_char_range(Some(CCursorRange::one(end)));
+                    state.store(ctx, msg_input_resp.id);
+                }
+
+                msg_input_resp.request_focus();
+            }
+
+            if msg_input_resp.changed() {
+                self.broadcast_typing();
+            }
+
             if utils::ui::input_submitted(&msg_input_resp, &ui) {
                 self.submit_message();
             }
@@ -427,24 +2889,99 @@ impl eframe::App for App {
                 msg_input_resp.request_focus();
             }
 
+            if ui.input(|inp| inp.modifiers.alt && inp.key_pressed(egui::Key::ArrowUp)) {
+                self.select_adjacent_message(-1);
+            }
+
+            if ui.input(|inp| inp.modifiers.alt && inp.key_pressed(egui::Key::ArrowDown)) {
+                self.select_adjacent_message(1);
+            }
+
+            if ui.input(|inp| inp.key_pressed(egui::Key::Escape)) {
+                self.selected_message = None;
+            }
+
+            // Shift has to be checked first, Ctrl+Tab matches Ctrl+Shift+Tab too
+            if ui.input_mut(|inp| inp.consume_key(Modifiers::CTRL | Modifiers::SHIFT, Key::Tab)) {
+                let id = self.tabs.select_adjacent(-1);
+                self.select_tab(id);
+            } else if ui.input_mut(|inp| inp.consume_key(Modifiers::CTRL, Key::Tab)) {
+                let id = self.tabs.select_adjacent(1);
+                self.select_tab(id);
+            }
+
             if self.poll_global_hotkeys() {
                 ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
                 msg_input_resp.request_focus();
             }
         });
 
+        egui::TopBottomPanel::top("tabs").show(ctx, |ui| {
+            if let Some((id, close)) = self.add_tab_bar(ui) {
+                match close {
+                    true => self.close_tab(id),
+                    false => self.select_tab(id),
+                }
+            }
+        });
+
         egui::CentralPanel::default()
             .frame(self.main_frame)
             .show(ctx, |ui| {
-                let msgs = &self.messages;
+                let msgs = &self.tabs.selected().messages;
+                let selected_message = self.selected_message;
                 let chat_scroll = ScrollArea::vertical().auto_shrink([false, false]);
+                let mut action: Option<(usize, MessageAction)> = None;
 
-                chat_scroll.show_rows(ui, 10.0, msgs.len(), |ui, row_range| {
+                let scroll_output = chat_scroll.show_rows(ui, 10.0, msgs.len(), |ui, row_range| {
                     for i in row_range {
                         let msg = &msgs[i];
-                        Self::add_label_for_message(ui, msg);
+                        let selected = match msg {
+                            GuiMessage::User(msg) => {
+                                selected_message == Some((msg.channel_id, msg.id))
+                            }
+                            _ => false,
+                        };
+
+                        if let Some(msg_action) = Self::add_label_for_message(ui, msg, selected) {
+                            action = Some((i, msg_action));
+                        }
                     }
                 });
+
+                match action {
+                    Some((idx, MessageAction::Select)) => self.toggle_selected_message(idx),
+                    Some((_, MessageAction::Download(attachment))) => {
+                        self.add_message(GuiMessage::Generic(format!(
+                            "Downloading {}...",
+                            attachment.filename
+                        )));
+                        self.transmit_to_dc(DiscordRequest::DownloadAttachment(attachment));
+                    }
+                    Some((_, MessageAction::Retry(id))) => {
+                        self.transmit_to_dc(DiscordRequest::OutboxRetry(id));
+                    }
+                    Some((_, MessageAction::Discard(id))) => {
+                        self.transmit_to_dc(DiscordRequest::OutboxDiscard(id));
+                    }
+                    None => (),
+                }
+
+                let scrollable = scroll_output.content_size.y > scroll_output.inner_rect.height();
+
+                let viewing_active = match (self.tabs.selected().id, &self.active_channel) {
+                    (TabId::Channel(id), Some(channel)) => id == channel.id,
+                    _ => false,
+                };
+
+                // Only when reaching the top, staying there doesn't load every page
+                let at_top = viewing_active && scrollable && scroll_output.state.offset.y <= 0.0;
+
+                if at_top && !self.scrolled_to_top {
+                    self.load_older_history();
+                }
+
+                self.scrolled_to_top = at_top;
             });
 
         ctx.request_repaint();
@@ -0,0 +1,471 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use serenity::async_trait;
+use tokio::sync::mpsc::Sender;
+
+use crate::{
+    backend::{ChatBackend, MessageSender},
+    discord::{
+        AttachmentInfo, ChannelInfo, ChannelKind, ChannelPermissions, ChatError, ChatMessage,
+        DiscordCommEvent, GuildSummary, Mention, MentionCandidate, OutgoingMessage, ReactionCount,
+        ReactionUpdate, UserSummary,
+    },
+    ratelimit::SendPacer,
+};
+
+pub const VALID_TOKEN: &str = "fake-token";
+pub const BOT_USER_ID: u64 = 1;
+
+/// Sent messages get IDs of `SENT_MESSAGE_ID_OFFSET + number of sent messages`.
+pub const SENT_MESSAGE_ID_OFFSET: u64 = 10_000;
+
+/// Direct message channels get IDs of `DM_CHANNEL_OFFSET + user ID`.
+const DM_CHANNEL_OFFSET: u64 = 1_000_000;
+
+#[derive(Default)]
+pub struct FakeState {
+    pub logged_in: bool,
+    pub guilds: Vec<(GuildSummary, Vec<ChannelInfo>)>,
+    pub sent: Vec<OutgoingMessage>,
+    pub history: Vec<ChatMessage>,
+    pub users: Vec<UserSummary>,
+    pub dm_channels: Vec<u64>,
+    pub typing: Vec<u64>,
+    pub pacer: SendPacer,
+    /// How long sending takes
+    pub send_latency: Duration,
+}
+
+/// In-memory backend for tests. Only [`VALID_TOKEN`] is accepted on login.
+/// Clones share the state, one is used for sending messages.
+#[derive(Clone)]
+pub struct FakeBackend {
+    tx: Sender<DiscordCommEvent>,
+    state: Arc<Mutex<FakeState>>,
+}
+
+/// Handle for inspecting a [`FakeBackend`] and simulating incoming events after it was moved.
+#[derive(Clone)]
+pub struct FakeRemote {
+    tx: Sender<DiscordCommEvent>,
+    state: Arc<Mutex<FakeState>>,
+}
+
+impl FakeBackend {
+    pub fn new(tx: Sender<DiscordCommEvent>) -> Self {
+        Self {
+            tx,
+            state: Arc::new(Mutex::new(FakeState::default())),
+        }
+    }
+
+    pub fn with_guild(self, id: u64, name: &str, channels: &[(u64, &str)]) -> Self {
+        let guild = GuildSummary {
+            id,
+            name: name.to_string(),
+        };
+
+        let channels = channels
+            .iter()
+            .map(|(channel_id, channel_name)| ChannelInfo {
+                id: *channel_id,
+                name: channel_name.to_string(),
+                guild_name: name.to_string(),
+                kind: ChannelKind::Text,
+                parent_id: None,
+                permissions: ChannelPermissions::default(),
+            })
+            .collect();
+
+        self.state.lock().unwrap().guilds.push((guild, channels));
+        self
+    }
+
+    pub fn with_channel(self, guild_id: u64, channel: ChannelInfo) -> Self {
+        let mut state = self.state.lock().unwrap();
+
+        if let Some((_, channels)) = state.guilds.iter_mut().find(|(g, _)| g.id == guild_id) {
+            channels.push(channel);
+        }
+
+        drop(state);
+        self
+    }
+
+    pub fn with_user(self, id: u64, name: &str) -> Self {
+        self.state.lock().unwrap().users.push(UserSummary {
+            id,
+            name: name.to_string(),
+        });
+        self
+    }
+
+    pub fn with_slowmode(self, channel_id: u64, slowmode: Duration) -> Self {
+        self.state
+            .lock()
+            .unwrap()
+            .pacer
+            .set_slowmode(channel_id, slowmode);
+        self
+    }
+
+    pub fn with_history(self, messages: Vec<ChatMessage>) -> Self {
+        self.state.lock().unwrap().history.extend(messages);
+        self
+    }
+
+    pub fn remote(&self) -> FakeRemote {
+        FakeRemote {
+            tx: self.tx.clone(),
+            state: self.state.clone(),
+        }
+    }
+
+    fn find_message(
+        state: &mut FakeState,
+        channel_id: u64,
+        message_id: u64,
+    ) -> Result<&mut ChatMessage, ChatError> {
+        state
+            .history
+            .iter_mut()
+            .find(|msg| msg.channel_id == channel_id && msg.id == message_id)
+            .ok_or(ChatError::NotFound("Unknown Message".to_string()))
+    }
+
+    fn check_logged_in(&self) -> Result<(), ChatError> {
+        if self.state.lock().unwrap().logged_in {
+            Ok(())
+        } else {
+            Err(ChatError::not_logged_in())
+        }
+    }
+}
+
+impl FakeRemote {
+    pub fn sent(&self) -> Vec<OutgoingMessage> {
+        self.state.lock().unwrap().sent.clone()
+    }
+
+    pub fn set_send_latency(&self, latency: Duration) {
+        self.state.lock().unwrap().send_latency = latency;
+    }
+
+    /// Channels in which typing was broadcast, in order.
+    pub fn typing(&self) -> Vec<u64> {
+        self.state.lock().unwrap().typing.clone()
+    }
+
+    pub async fn receive_message(&self, msg: ChatMessage) {
+        if !self.state.lock().unwrap().logged_in {
+            return;
+        }
+
+        self.tx
+            .send(DiscordCommEvent::MessageReceived(msg))
+            .await
+            .expect("Failed to send FakeRemote -> App");
+    }
+}
+
+#[async_trait]
+impl MessageSender for FakeBackend {
+    async fn is_ready(&mut self) -> bool {
+        self.state.lock().unwrap().logged_in
+    }
+
+    async fn send_message(&mut self, msg: OutgoingMessage) -> Result<u64, ChatError> {
+        self.check_logged_in()?;
+
+        // Like waiting out a rate limit
+        let latency = self.state.lock().unwrap().send_latency;
+        tokio::time::sleep(latency).await;
+
+        let is_dm = self
+            .state
+            .lock()
+            .unwrap()
+            .dm_channels
+            .contains(&msg.channel_id);
+
+        if !is_dm {
+            let channel = self.get_channel(msg.channel_id).await?;
+
+            // Webhooks don't need the bot to be allowed to send messages
+            let allowed = match msg.webhook {
+                Some(_) => channel.permissions.webhooks,
+                None => channel.permissions.send,
+            };
+
+            if !allowed {
+                return Err(ChatError::Permission(
+                    "Unable to send message: Missing Permissions".to_string(),
+                ));
+            }
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+
+        // Discord rejects messages sent too early in slowmode
+        if let Some(wait) = state.pacer.wait_time(msg.channel_id, now) {
+            return Err(ChatError::RateLimited(Some(wait)));
+        }
+
+        state.pacer.sent(msg.channel_id, now);
+        state.sent.push(msg);
+
+        Ok(SENT_MESSAGE_ID_OFFSET + state.sent.len() as u64)
+    }
+
+    async fn send_delay(&mut self, channel_id: u64) -> Option<Duration> {
+        let state = self.state.lock().unwrap();
+        state.pacer.wait_time(channel_id, Instant::now())
+    }
+}
+
+#[async_trait]
+impl ChatBackend for FakeBackend {
+    type Sender = FakeBackend;
+
+    async fn login(&mut self, token: String) -> Result<(), ChatError> {
+        if token != VALID_TOKEN {
+            return Err(ChatError::Auth("Invalid token".to_string()));
+        }
+
+        self.state.lock().unwrap().logged_in = true;
+
+        self.tx
+            .send(DiscordCommEvent::Ready(UserSummary {
+                id: BOT_USER_ID,
+                name: "Dove".to_string(),
+            }))
+            .await
+            .map_err(|e| ChatError::Internal(e.to_string()))
+    }
+
+    async fn logout(&mut self) {
+        self.state.lock().unwrap().logged_in = false;
+    }
+
+    fn message_sender(&self) -> FakeBackend {
+        self.clone()
+    }
+
+    async fn list_guilds(&mut self) -> Result<Vec<GuildSummary>, ChatError> {
+        self.check_logged_in()?;
+
+        let state = self.state.lock().unwrap();
+        Ok(state
+            .guilds
+            .iter()
+            .map(|(guild, _)| guild.clone())
+            .collect())
+    }
+
+    async fn list_channels(&mut self, guild_id: u64) -> Result<Vec<ChannelInfo>, ChatError> {
+        self.check_logged_in()?;
+
+        let state = self.state.lock().unwrap();
+
+        state
+            .guilds
+            .iter()
+            .find(|(guild, _)| guild.id == guild_id)
+            .map(|(_, channels)| channels.clone())
+            .ok_or(ChatError::NotFound(
+                "Unable to get server: Unknown Guild".to_string(),
+            ))
+    }
+
+    async fn get_channel(&mut self, channel_id: u64) -> Result<ChannelInfo, ChatError> {
+        self.check_logged_in()?;
+
+        let state = self.state.lock().unwrap();
+
+        state
+            .guilds
+            .iter()
+            .flat_map(|(_, channels)| channels)
+            .find(|channel| channel.id == channel_id)
+            .cloned()
+            .ok_or(ChatError::NotFound(
+                "Unable to get channel: Unknown Channel".to_string(),
+            ))
+    }
+
+    async fn get_history(
+        &mut self,
+        channel_id: u64,
+        before: Option<u64>,
+        limit: u8,
+    ) -> Result<Vec<ChatMessage>, ChatError> {
+        self.check_logged_in()?;
+
+        let state = self.state.lock().unwrap();
+
+        let mut messages: Vec<ChatMessage> = state
+            .history
+            .iter()
+            .filter(|msg| msg.channel_id == channel_id)
+            .filter(|msg| before.is_none_or(|before| msg.id < before))
+            .cloned()
+            .collect();
+
+        messages.sort_by_key(|msg| msg.id);
+
+        let skip = messages.len().saturating_sub(limit as usize);
+        Ok(messages.split_off(skip))
+    }
+
+    async fn add_reaction(
+        &mut self,
+        channel_id: u64,
+        message_id: u64,
+        emoji: String,
+    ) -> Result<(), ChatError> {
+        self.check_logged_in()?;
+
+        {
+            let mut state = self.state.lock().unwrap();
+            let msg = Self::find_message(&mut state, channel_id, message_id)?;
+
+            match msg.reactions.iter_mut().find(|r| r.emoji == emoji) {
+                Some(reaction) if reaction.me => return Ok(()),
+                Some(reaction) => {
+                    reaction.count += 1;
+                    reaction.me = true;
+                }
+                None => msg.reactions.push(ReactionCount {
+                    emoji: emoji.to_owned(),
+                    count: 1,
+                    me: true,
+                }),
+            }
+        }
+
+        let update = ReactionUpdate {
+            channel_id,
+            message_id,
+            emoji,
+            me: true,
+        };
+
+        self.tx
+            .send(DiscordCommEvent::ReactionAdded(update))
+            .await
+            .map_err(|e| ChatError::Internal(e.to_string()))
+    }
+
+    async fn remove_reaction(
+        &mut self,
+        channel_id: u64,
+        message_id: u64,
+        emoji: String,
+    ) -> Result<(), ChatError> {
+        self.check_logged_in()?;
+
+        {
+            let mut state = self.state.lock().unwrap();
+            let msg = Self::find_message(&mut state, channel_id, message_id)?;
+
+            let idx = msg
+                .reactions
+                .iter()
+                .position(|r| r.emoji == emoji && r.me)
+                .ok_or(ChatError::NotFound("Unknown Emoji".to_string()))?;
+
+            msg.reactions[idx].count -= 1;
+            msg.reactions[idx].me = false;
+            msg.reactions.retain(|r| r.count > 0);
+        }
+
+        let update = ReactionUpdate {
+            channel_id,
+            message_id,
+            emoji,
+            me: true,
+        };
+
+        self.tx
+            .send(DiscordCommEvent::ReactionRemoved(update))
+            .await
+            .map_err(|e| ChatError::Internal(e.to_string()))
+    }
+
+    async fn download_attachment(
+        &mut self,
+        attachment: &AttachmentInfo,
+    ) -> Result<Vec<u8>, ChatError> {
+        self.check_logged_in()?;
+
+        // Content is the file extension, enough to tell files apart
+        let extension = attachment.filename.rsplit('.').next().unwrap_or_default();
+        Ok(extension.as_bytes().to_vec())
+    }
+
+    async fn find_user(&mut self, query: String) -> Result<UserSummary, ChatError> {
+        self.check_logged_in()?;
+
+        let state = self.state.lock().unwrap();
+
+        state
+            .users
+            .iter()
+            .find(|user| user.id.to_string() == query || user.name.eq_ignore_ascii_case(&query))
+            .cloned()
+            .ok_or(ChatError::NotFound(format!("User '{}' not found", query)))
+    }
+
+    async fn open_direct_channel(&mut self, user_id: u64) -> Result<u64, ChatError> {
+        self.check_logged_in()?;
+
+        let channel_id = DM_CHANNEL_OFFSET + user_id;
+        let mut state = self.state.lock().unwrap();
+
+        if !state.dm_channels.contains(&channel_id) {
+            state.dm_channels.push(channel_id);
+        }
+
+        Ok(channel_id)
+    }
+
+    async fn list_mention_candidates(
+        &mut self,
+        channel_id: u64,
+    ) -> Result<Vec<MentionCandidate>, ChatError> {
+        self.check_logged_in()?;
+
+        let state = self.state.lock().unwrap();
+
+        let Some((_, channels)) = state
+            .guilds
+            .iter()
+            .find(|(_, channels)| channels.iter().any(|c| c.id == channel_id))
+        else {
+            return Ok(Vec::new());
+        };
+
+        let members = state.users.iter().map(|user| MentionCandidate {
+            mention: Mention::User(user.id),
+            name: user.name.to_owned(),
+        });
+
+        let channels = channels.iter().map(|channel| MentionCandidate {
+            mention: Mention::Channel(channel.id),
+            name: channel.name.to_owned(),
+        });
+
+        Ok(members.chain(channels).collect())
+    }
+
+    async fn broadcast_typing(&mut self, channel_id: u64) -> Result<(), ChatError> {
+        self.get_channel(channel_id).await?;
+
+        self.state.lock().unwrap().typing.push(channel_id);
+        Ok(())
+    }
+}
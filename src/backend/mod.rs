@@ -0,0 +1,995 @@
+use std::{sync::Arc, time::Duration};
+
+use serenity::async_trait;
+use tokio::sync::{
+    Mutex,
+    mpsc::{self, Sender, UnboundedReceiver, UnboundedSender},
+};
+
+use crate::{
+    backend::worker::{OutboxState, OutboxWorker},
+    discord::{
+        self, AttachmentInfo, ChannelInfo, ChannelKind, ChatError, ChatMessage, DirectConversation,
+        DiscordCommEvent, DiscordRequest, DiscordResponse, GuildSummary, MentionCandidate,
+        OutgoingMessage, RequestEnvelope, UserSummary,
+    },
+    outbox::OutboxStatus,
+};
+
+#[cfg(test)]
+pub mod fake;
+mod worker;
+
+/// Connection to a chat service.
+/// Incoming events (messages, readiness) are pushed to the GUI by the implementation itself.
+#[async_trait]
+pub trait ChatBackend: Send {
+    type Sender: MessageSender;
+
+    async fn login(&mut self, token: String) -> Result<(), ChatError>;
+    async fn logout(&mut self);
+    /// Returns a handle that sends messages on this connection alongside other requests.
+    fn message_sender(&self) -> Self::Sender;
+    async fn list_guilds(&mut self) -> Result<Vec<GuildSummary>, ChatError>;
+    async fn list_channels(&mut self, guild_id: u64) -> Result<Vec<ChannelInfo>, ChatError>;
+    async fn get_channel(&mut self, channel_id: u64) -> Result<ChannelInfo, ChatError>;
+    /// Returns up to `limit` messages sent before `before` (or the latest ones), oldest first.
+    async fn get_history(
+        &mut self,
+        channel_id: u64,
+        before: Option<u64>,
+        limit: u8,
+    ) -> Result<Vec<ChatMessage>, ChatError>;
+    async fn add_reaction(
+        &mut self,
+        channel_id: u64,
+        message_id: u64,
+        emoji: String,
+    ) -> Result<(), ChatError>;
+    async fn remove_reaction(
+        &mut self,
+        channel_id: u64,
+        message_id: u64,
+        emoji: String,
+    ) -> Result<(), ChatError>;
+    async fn download_attachment(
+        &mut self,
+        attachment: &AttachmentInfo,
+    ) -> Result<Vec<u8>, ChatError>;
+    /// Finds a user by ID or name.
+    async fn find_user(&mut self, query: String) -> Result<UserSummary, ChatError>;
+    /// Returns the ID of the direct message channel with the user.
+    async fn open_direct_channel(&mut self, user_id: u64) -> Result<u64, ChatError>;
+    /// Lists members and channels that can be mentioned in the channel.
+    async fn list_mention_candidates(
+        &mut self,
+        channel_id: u64,
+    ) -> Result<Vec<MentionCandidate>, ChatError>;
+    /// Shows the bot as typing in the channel for [`discord::TYPING_DURATION`].
+    async fn broadcast_typing(&mut self, channel_id: u64) -> Result<(), ChatError>;
+}
+
+/// Sends messages of a [`ChatBackend`], can be used while the backend handles other requests.
+#[async_trait]
+pub trait MessageSender: Send + Sync + 'static {
+    /// Returns true if logged in and connected, so messages can be sent.
+    async fn is_ready(&mut self) -> bool;
+    /// Returns the ID of the sent message.
+    async fn send_message(&mut self, msg: OutgoingMessage) -> Result<u64, ChatError>;
+    /// Returns how long sending to the channel has to wait because of slowmode or rate limits.
+    async fn send_delay(&mut self, channel_id: u64) -> Option<Duration>;
+}
+
+/// Executes GUI requests on a backend and reports the results back to the GUI.
+pub struct Dispatcher<B: ChatBackend> {
+    backend: B,
+    tx: Sender<DiscordCommEvent>,
+    /// Shared with the [`OutboxWorker`], which sends the queued messages
+    sender: Arc<Mutex<B::Sender>>,
+    outbox: Arc<Mutex<OutboxState>>,
+    flushes: UnboundedSender<()>,
+    /// Taken by the [`OutboxWorker`] when the dispatcher starts
+    flush_requests: Option<UnboundedReceiver<()>>,
+    /// The outbox is flushed after responding, so the response comes before the sent messages
+    flush_requested: bool,
+}
+
+impl<B: ChatBackend> Dispatcher<B> {
+    pub fn new(backend: B, tx: Sender<DiscordCommEvent>) -> Self {
+        let (flushes, flush_requests) = mpsc::unbounded_channel();
+
+        Self {
+            sender: Arc::new(Mutex::new(backend.message_sender())),
+            outbox: Arc::new(Mutex::new(OutboxState::new(tx.clone(), false))),
+            backend,
+            tx,
+            flushes,
+            flush_requests: Some(flush_requests),
+            flush_requested: false,
+        }
+    }
+
+    /// Saves the outbox on every change. Messages left unsent by the last session are restored
+    /// when the GUI switches to their account.
+    pub fn with_saved_outbox(mut self) -> Self {
+        self.outbox = Arc::new(Mutex::new(OutboxState::new(self.tx.clone(), true)));
+        self
+    }
+
+    async fn send_to_gui(&self, event: DiscordCommEvent) {
+        self.tx.send(event).await.unwrap_or_else(|err| {
+            eprintln!("Failed to send Dispatcher -> App: {:?}", err);
+        });
+    }
+
+    async fn join_channel_by_name(
+        &mut self,
+        guild_query: String,
+        channel_name: String,
+    ) -> Result<ChannelInfo, ChatError> {
+        let guilds = self.backend.list_guilds().await?;
+
+        let guild = guilds
+            .iter()
+            .find(|g| g.id.to_string() == guild_query || g.name.eq_ignore_ascii_case(&guild_query))
+            .ok_or(ChatError::NotFound(format!(
+                "Server '{}' not found",
+                guild_query
+            )))?;
+
+        let channels = self.backend.list_channels(guild.id).await?;
+
+        channels
+            .into_iter()
+            .find(|c| c.name.eq_ignore_ascii_case(&channel_name))
+            .ok_or(ChatError::NotFound(format!(
+                "Channel '#{}' not found in '{}'",
+                channel_name, guild.name
+            )))
+    }
+
+    fn check_joinable(channel: ChannelInfo) -> Result<ChannelInfo, ChatError> {
+        if channel.kind == ChannelKind::Forum {
+            return Err(ChatError::InvalidInput(format!(
+                "#{} is a forum, join one of its posts instead. Use /channels to see them",
+                channel.name
+            )));
+        }
+
+        Ok(channel)
+    }
+
+    pub async fn process_request(
+        &mut self,
+        request: DiscordRequest,
+    ) -> Result<DiscordResponse, ChatError> {
+        let response = match request {
+            DiscordRequest::Logout => {
+                // A message being sent is recorded in the outbox of the account that sent it
+                let _sender = self.sender.lock().await;
+
+                self.backend.logout().await;
+                DiscordResponse::Done
+            }
+            DiscordRequest::Login(token) => {
+                self.backend.login(token).await?;
+                DiscordResponse::Done
+            }
+            DiscordRequest::SwitchAccount(account) => {
+                // Each account has its own unsent messages
+                self.outbox.lock().await.switch_account(account).await;
+                DiscordResponse::Done
+            }
+            DiscordRequest::MessageSend(msg) => {
+                self.outbox.lock().await.push(msg).await;
+                self.flush_requested = true;
+                DiscordResponse::Done
+            }
+            DiscordRequest::OutboxFlush => {
+                self.flush_requested = true;
+                DiscordResponse::Done
+            }
+            DiscordRequest::OutboxRetry(id) => {
+                self.outbox
+                    .lock()
+                    .await
+                    .set_status(id, OutboxStatus::Pending)
+                    .await;
+                self.flush_requested = true;
+                DiscordResponse::Done
+            }
+            DiscordRequest::OutboxDiscard(id) => {
+                self.outbox.lock().await.remove(id).await?;
+                DiscordResponse::Done
+            }
+            DiscordRequest::GetGuilds => {
+                DiscordResponse::GuildsListed(self.backend.list_guilds().await?)
+            }
+            DiscordRequest::GetAvailableTextChannels(guild_id) => {
+                DiscordResponse::AvailableTextChannelsListed(
+                    self.backend.list_channels(guild_id).await?,
+                )
+            }
+            DiscordRequest::JoinChannel(channel_id) => {
+                let channel = Self::check_joinable(self.backend.get_channel(channel_id).await?)?;
+
+                DiscordResponse::ChannelJoined(channel)
+            }
+            DiscordRequest::RefreshChannel(channel_id) => {
+                DiscordResponse::ChannelRefreshed(self.backend.get_channel(channel_id).await?)
+            }
+            DiscordRequest::JoinChannelByName(guild_query, channel_name) => {
+                let channel = self.join_channel_by_name(guild_query, channel_name).await?;
+
+                DiscordResponse::ChannelJoined(Self::check_joinable(channel)?)
+            }
+            DiscordRequest::GetHistory(channel_id, before) => {
+                let messages = self
+                    .backend
+                    .get_history(channel_id, before, discord::HISTORY_PAGE_SIZE)
+                    .await?;
+
+                DiscordResponse::HistoryLoaded(channel_id, messages)
+            }
+            DiscordRequest::ReactionAdd(channel_id, message_id, emoji) => {
+                self.backend
+                    .add_reaction(channel_id, message_id, emoji)
+                    .await?;
+                DiscordResponse::Done
+            }
+            DiscordRequest::ReactionRemove(channel_id, message_id, emoji) => {
+                self.backend
+                    .remove_reaction(channel_id, message_id, emoji)
+                    .await?;
+                DiscordResponse::Done
+            }
+            DiscordRequest::DownloadAttachment(attachment) => {
+                let data = self.backend.download_attachment(&attachment).await?;
+
+                DiscordResponse::AttachmentDownloaded(attachment, data)
+            }
+            DiscordRequest::DirectMessageSend(recipient, parts) => {
+                let user = self.backend.find_user(recipient).await?;
+                let channel_id = self.backend.open_direct_channel(user.id).await?;
+                let mut outbox = self.outbox.lock().await;
+
+                for content in parts {
+                    outbox
+                        .push(OutgoingMessage {
+                            channel_id,
+                            content,
+                            reply_to: None,
+                            attachments: Vec::new(),
+                            webhook: None,
+                        })
+                        .await;
+                }
+
+                self.flush_requested = true;
+                DiscordResponse::DirectMessageSent(DirectConversation { channel_id, user })
+            }
+            DiscordRequest::TypingSend(channel_id) => {
+                self.backend.broadcast_typing(channel_id).await?;
+                DiscordResponse::Done
+            }
+            DiscordRequest::GetMentionCandidates(channel_id) => {
+                let candidates = self.backend.list_mention_candidates(channel_id).await?;
+
+                DiscordResponse::MentionCandidatesListed(channel_id, candidates)
+            }
+        };
+
+        Ok(response)
+    }
+
+    /// Processes requests one at a time, so responses arrive in the order of the requests.
+    /// Messages are answered once queued, an [`OutboxWorker`] sends them in the background.
+    pub async fn start(&mut self, mut rx: UnboundedReceiver<RequestEnvelope>) {
+        if let Some(flush_requests) = self.flush_requests.take() {
+            let worker = OutboxWorker::new(
+                self.sender.clone(),
+                self.outbox.clone(),
+                self.tx.clone(),
+                flush_requests,
+            );
+
+            tokio::spawn(worker.start());
+        }
+
+        while let Some(RequestEnvelope { id, request }) = rx.recv().await {
+            let res = self.process_request(request).await;
+
+            self.send_to_gui(DiscordCommEvent::Responded(id, res)).await;
+
+            if std::mem::take(&mut self.flush_requested) {
+                self.flushes.send(()).unwrap_or_else(|err| {
+                    eprintln!("Failed to send Dispatcher -> OutboxWorker: {:?}", err);
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        path::PathBuf,
+        sync::atomic::{AtomicU64, Ordering},
+        time::Duration,
+    };
+
+    use tokio::{
+        sync::mpsc::{self, Receiver, UnboundedSender},
+        time::timeout,
+    };
+
+    use crate::{
+        app::REQUEST_TIMEOUT,
+        backend::{
+            Dispatcher,
+            fake::{FakeBackend, FakeRemote, SENT_MESSAGE_ID_OFFSET, VALID_TOKEN},
+        },
+        discord::{
+            self, AttachmentInfo, ChannelInfo, ChannelKind, ChannelPermissions, ChatError,
+            ChatMessage, DiscordCommEvent, DiscordRequest, DiscordResponse, Mention,
+            OutgoingMessage, RequestEnvelope, WebhookIdentity,
+        },
+        outbox::{OutboxEntry, OutboxStatus},
+        utils::comm::{COMM_BUFFER_SIZE, MPSCChannel, UnboundedMPSCChannel},
+    };
+
+    fn chat_message(id: u64, channel_id: u64, content: &str) -> ChatMessage {
+        ChatMessage {
+            id,
+            channel_id,
+            guild_id: Some(2),
+            author_id: 3,
+            author_name: "Wolfyxon".to_string(),
+            content: content.to_string(),
+            edited: false,
+            reply_to: None,
+            reactions: Vec::new(),
+            attachments: Vec::new(),
+            embeds: Vec::new(),
+            mentions_me: false,
+            from_webhook: false,
+        }
+    }
+
+    fn channel(id: u64, name: &str, kind: ChannelKind, parent_id: Option<u64>) -> ChannelInfo {
+        ChannelInfo {
+            id,
+            name: name.to_string(),
+            guild_name: "Other".to_string(),
+            kind,
+            parent_id,
+            permissions: ChannelPermissions::default(),
+        }
+    }
+
+    fn read_only_channel(id: u64, name: &str) -> ChannelInfo {
+        let mut channel = channel(id, name, ChannelKind::Announcement, None);
+        channel.permissions.send = false;
+        channel.permissions.webhooks = false;
+        channel
+    }
+
+    async fn expect_outbox(rx: &mut Receiver<DiscordCommEvent>) -> OutboxEntry {
+        match rx.recv().await {
+            Some(DiscordCommEvent::OutboxUpdated(entry)) => entry,
+            other => panic!("Unexpected event: {:?}", other),
+        }
+    }
+
+    fn outgoing(channel_id: u64, content: &str, reply_to: Option<u64>) -> OutgoingMessage {
+        OutgoingMessage {
+            channel_id,
+            content: content.to_string(),
+            reply_to,
+            attachments: Vec::new(),
+            webhook: None,
+        }
+    }
+
+    fn start_fake() -> (
+        UnboundedSender<RequestEnvelope>,
+        Receiver<DiscordCommEvent>,
+        FakeRemote,
+    ) {
+        let (tx_dc_to_gui, rx_dc_to_gui): MPSCChannel<DiscordCommEvent> =
+            mpsc::channel(COMM_BUFFER_SIZE);
+        let (tx_gui_to_dc, rx_gui_to_dc): UnboundedMPSCChannel<RequestEnvelope> =
+            mpsc::unbounded_channel();
+
+        let backend = FakeBackend::new(tx_dc_to_gui.clone())
+            .with_guild(1, "Dove Testers", &[(10, "general"), (11, "off-topic")])
+            .with_guild(2, "Other", &[(20, "general")])
+            .with_channel(2, channel(21, "ideas", ChannelKind::Forum, None))
+            .with_channel(2, channel(22, "Dark mode", ChannelKind::Thread, Some(21)))
+            .with_channel(2, read_only_channel(23, "announcements"))
+            .with_slowmode(11, Duration::from_secs(60))
+            .with_user(3, "Wolfyxon")
+            .with_history((1..=120).map(|id| chat_message(id, 20, "old")).collect());
+        let remote = backend.remote();
+
+        tokio::spawn(async move {
+            Dispatcher::new(backend, tx_dc_to_gui)
+                .start(rx_gui_to_dc)
+                .await;
+        });
+
+        (tx_gui_to_dc, rx_dc_to_gui, remote)
+    }
+
+    /// Sends a request and returns its ID.
+    fn send(tx: &UnboundedSender<RequestEnvelope>, request: DiscordRequest) -> u64 {
+        static LAST_ID: AtomicU64 = AtomicU64::new(0);
+        let id = LAST_ID.fetch_add(1, Ordering::Relaxed) + 1;
+
+        tx.send(RequestEnvelope { id, request }).unwrap();
+        id
+    }
+
+    /// Expects the next event to be the response to the request with the given ID.
+    async fn expect_response(
+        rx: &mut Receiver<DiscordCommEvent>,
+        id: u64,
+    ) -> Result<DiscordResponse, ChatError> {
+        match rx.recv().await {
+            Some(DiscordCommEvent::Responded(responded_id, res)) if responded_id == id => res,
+            other => panic!("Unexpected event: {:?}", other),
+        }
+    }
+
+    async fn expect_done(rx: &mut Receiver<DiscordCommEvent>, id: u64) {
+        assert!(matches!(
+            expect_response(rx, id).await,
+            Ok(DiscordResponse::Done)
+        ));
+    }
+
+    async fn login(tx: &UnboundedSender<RequestEnvelope>, rx: &mut Receiver<DiscordCommEvent>) {
+        let id = send(tx, DiscordRequest::Login(VALID_TOKEN.to_string()));
+
+        assert!(matches!(rx.recv().await, Some(DiscordCommEvent::Ready(_))));
+        expect_done(rx, id).await;
+    }
+
+    #[tokio::test]
+    async fn test_requires_login() {
+        let (tx, mut rx, _remote) = start_fake();
+
+        let id = send(&tx, DiscordRequest::GetGuilds);
+        assert!(matches!(
+            expect_response(&mut rx, id).await,
+            Err(ChatError::Auth(_))
+        ));
+
+        let id = send(&tx, DiscordRequest::Login("wrong".to_string()));
+        assert!(matches!(
+            expect_response(&mut rx, id).await,
+            Err(ChatError::Auth(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_responses_in_order() {
+        let (tx, mut rx, _remote) = start_fake();
+        login(&tx, &mut rx).await;
+
+        let ids = [
+            send(&tx, DiscordRequest::JoinChannel(10)),
+            send(&tx, DiscordRequest::JoinChannel(99)),
+            send(&tx, DiscordRequest::GetGuilds),
+        ];
+
+        assert!(matches!(
+            expect_response(&mut rx, ids[0]).await,
+            Ok(DiscordResponse::ChannelJoined(channel)) if channel.id == 10
+        ));
+        assert!(matches!(
+            expect_response(&mut rx, ids[1]).await,
+            Err(ChatError::NotFound(_))
+        ));
+        assert!(matches!(
+            expect_response(&mut rx, ids[2]).await,
+            Ok(DiscordResponse::GuildsListed(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_list_and_join() {
+        let (tx, mut rx, _remote) = start_fake();
+        login(&tx, &mut rx).await;
+
+        let id = send(&tx, DiscordRequest::GetGuilds);
+
+        match expect_response(&mut rx, id).await {
+            Ok(DiscordResponse::GuildsListed(guilds)) => assert_eq!(guilds.len(), 2),
+            other => panic!("Unexpected response: {:?}", other),
+        }
+
+        let id = send(&tx, DiscordRequest::GetAvailableTextChannels(1));
+
+        match expect_response(&mut rx, id).await {
+            Ok(DiscordResponse::AvailableTextChannelsListed(channels)) => {
+                assert_eq!(channels.len(), 2)
+            }
+            other => panic!("Unexpected response: {:?}", other),
+        }
+
+        let id = send(
+            &tx,
+            DiscordRequest::JoinChannelByName("dove testers".to_string(), "OFF-TOPIC".to_string()),
+        );
+
+        match expect_response(&mut rx, id).await {
+            Ok(DiscordResponse::ChannelJoined(channel)) => {
+                assert_eq!(channel.id, 11);
+                assert_eq!(channel.guild_name, "Dove Testers");
+            }
+            other => panic!("Unexpected response: {:?}", other),
+        }
+
+        let id = send(&tx, DiscordRequest::JoinChannel(99));
+        assert!(matches!(
+            expect_response(&mut rx, id).await,
+            Err(ChatError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_threads_and_forums() {
+        let (tx, mut rx, remote) = start_fake();
+        login(&tx, &mut rx).await;
+
+        let id = send(
+            &tx,
+            DiscordRequest::JoinChannelByName("Other".to_string(), "ideas".to_string()),
+        );
+        assert!(matches!(
+            expect_response(&mut rx, id).await,
+            Err(ChatError::InvalidInput(_))
+        ));
+
+        let id = send(&tx, DiscordRequest::JoinChannel(22));
+
+        match expect_response(&mut rx, id).await {
+            Ok(DiscordResponse::ChannelJoined(channel)) => {
+                assert_eq!(channel.kind, ChannelKind::Thread);
+                assert_eq!(channel.parent_id, Some(21));
+            }
+            other => panic!("Unexpected response: {:?}", other),
+        }
+
+        let id = send(&tx, DiscordRequest::MessageSend(outgoing(22, "+1", None)));
+
+        assert_eq!(expect_outbox(&mut rx).await.status, OutboxStatus::Pending);
+        expect_done(&mut rx, id).await;
+        assert!(matches!(
+            expect_outbox(&mut rx).await.status,
+            OutboxStatus::Sent(_)
+        ));
+        assert_eq!(remote.sent(), vec![outgoing(22, "+1", None)]);
+    }
+
+    #[tokio::test]
+    async fn test_read_only_channel() {
+        let (tx, mut rx, remote) = start_fake();
+        login(&tx, &mut rx).await;
+
+        let id = send(&tx, DiscordRequest::JoinChannel(23));
+
+        match expect_response(&mut rx, id).await {
+            Ok(DiscordResponse::ChannelJoined(channel)) => assert!(!channel.permissions.send),
+            other => panic!("Unexpected response: {:?}", other),
+        }
+
+        let request_id = send(&tx, DiscordRequest::MessageSend(outgoing(23, "Hi", None)));
+
+        let id = expect_outbox(&mut rx).await.id;
+        expect_done(&mut rx, request_id).await;
+        assert!(matches!(
+            expect_outbox(&mut rx).await.status,
+            OutboxStatus::Failed(_)
+        ));
+
+        let request_id = send(&tx, DiscordRequest::OutboxRetry(id));
+
+        assert_eq!(expect_outbox(&mut rx).await.status, OutboxStatus::Pending);
+        expect_done(&mut rx, request_id).await;
+        assert!(matches!(
+            expect_outbox(&mut rx).await.status,
+            OutboxStatus::Failed(_)
+        ));
+
+        let request_id = send(&tx, DiscordRequest::OutboxDiscard(id));
+
+        assert!(matches!(
+            rx.recv().await,
+            Some(DiscordCommEvent::OutboxDiscarded(discarded)) if discarded == id
+        ));
+        expect_done(&mut rx, request_id).await;
+        assert!(remote.sent().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_send_and_receive() {
+        let (tx, mut rx, remote) = start_fake();
+        login(&tx, &mut rx).await;
+
+        let id = send(
+            &tx,
+            DiscordRequest::MessageSend(outgoing(20, "Hello", Some(7))),
+        );
+
+        assert_eq!(expect_outbox(&mut rx).await.status, OutboxStatus::Pending);
+        expect_done(&mut rx, id).await;
+        assert_eq!(
+            expect_outbox(&mut rx).await.status,
+            OutboxStatus::Sent(SENT_MESSAGE_ID_OFFSET + 1)
+        );
+
+        let id = send(&tx, DiscordRequest::Logout);
+        expect_done(&mut rx, id).await;
+
+        let id = send(
+            &tx,
+            DiscordRequest::MessageSend(outgoing(20, "Queued", None)),
+        );
+
+        // Stays pending while offline
+        let queued = expect_outbox(&mut rx).await;
+        assert_eq!(queued.status, OutboxStatus::Pending);
+        expect_done(&mut rx, id).await;
+        assert_eq!(remote.sent(), vec![outgoing(20, "Hello", Some(7))]);
+
+        login(&tx, &mut rx).await;
+        let id = send(&tx, DiscordRequest::OutboxFlush);
+        expect_done(&mut rx, id).await;
+
+        let flushed = expect_outbox(&mut rx).await;
+        assert_eq!(flushed.id, queued.id);
+        assert_eq!(
+            flushed.status,
+            OutboxStatus::Sent(SENT_MESSAGE_ID_OFFSET + 2)
+        );
+        assert_eq!(
+            remote.sent(),
+            vec![outgoing(20, "Hello", Some(7)), outgoing(20, "Queued", None)]
+        );
+
+        let msg = chat_message(500, 20, "Hi");
+        remote.receive_message(msg.clone()).await;
+
+        match rx.recv().await {
+            Some(DiscordCommEvent::MessageReceived(received)) => assert_eq!(received, msg),
+            other => panic!("Unexpected event: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_switch_account() {
+        let (tx, mut rx, remote) = start_fake();
+
+        let id = send(
+            &tx,
+            DiscordRequest::MessageSend(outgoing(20, "Queued", None)),
+        );
+        assert_eq!(expect_outbox(&mut rx).await.status, OutboxStatus::Pending);
+        expect_done(&mut rx, id).await;
+
+        // Messages queued by one account aren't sent by another
+        let id = send(&tx, DiscordRequest::SwitchAccount("test".to_string()));
+        expect_done(&mut rx, id).await;
+
+        login(&tx, &mut rx).await;
+        let id = send(&tx, DiscordRequest::OutboxFlush);
+        expect_done(&mut rx, id).await;
+        assert!(remote.sent().is_empty());
+
+        // A message being sent while switching is sent by its own account, or not at all
+        remote.set_send_latency(Duration::from_millis(200));
+
+        let id = send(&tx, DiscordRequest::MessageSend(outgoing(20, "Hi", None)));
+        assert_eq!(expect_outbox(&mut rx).await.status, OutboxStatus::Pending);
+        expect_done(&mut rx, id).await;
+
+        let logout = send(&tx, DiscordRequest::Logout);
+        let switch = send(&tx, DiscordRequest::SwitchAccount("other".to_string()));
+
+        let sent = match rx.recv().await {
+            Some(DiscordCommEvent::OutboxUpdated(entry)) => {
+                assert!(matches!(entry.status, OutboxStatus::Sent(_)));
+                expect_done(&mut rx, logout).await;
+                true
+            }
+            Some(DiscordCommEvent::Responded(id, Ok(DiscordResponse::Done))) if id == logout => {
+                false
+            }
+            other => panic!("Unexpected event: {:?}", other),
+        };
+
+        expect_done(&mut rx, switch).await;
+        login(&tx, &mut rx).await;
+        let id = send(&tx, DiscordRequest::OutboxFlush);
+        expect_done(&mut rx, id).await;
+
+        assert_eq!(remote.sent().len(), sent as usize);
+    }
+
+    #[tokio::test]
+    async fn test_slowmode() {
+        let (tx, mut rx, remote) = start_fake();
+        login(&tx, &mut rx).await;
+
+        let expect_delay = async |rx: &mut Receiver<DiscordCommEvent>| match rx.recv().await {
+            Some(DiscordCommEvent::SendDelayed(Some(11), delay)) => {
+                assert!(delay > Duration::from_secs(55) && delay <= Duration::from_secs(60));
+            }
+            other => panic!("Unexpected event: {:?}", other),
+        };
+
+        let id = send(&tx, DiscordRequest::MessageSend(outgoing(11, "One", None)));
+
+        assert_eq!(expect_outbox(&mut rx).await.status, OutboxStatus::Pending);
+        expect_done(&mut rx, id).await;
+        assert!(matches!(
+            expect_outbox(&mut rx).await.status,
+            OutboxStatus::Sent(_)
+        ));
+        expect_delay(&mut rx).await;
+
+        // Waits in the outbox instead of failing
+        let id = send(&tx, DiscordRequest::MessageSend(outgoing(11, "Two", None)));
+
+        assert_eq!(expect_outbox(&mut rx).await.status, OutboxStatus::Pending);
+        expect_done(&mut rx, id).await;
+        expect_delay(&mut rx).await;
+
+        // Other channels aren't held up
+        let id = send(
+            &tx,
+            DiscordRequest::MessageSend(outgoing(10, "Three", None)),
+        );
+
+        assert_eq!(expect_outbox(&mut rx).await.status, OutboxStatus::Pending);
+        expect_done(&mut rx, id).await;
+        expect_delay(&mut rx).await;
+
+        let sent = expect_outbox(&mut rx).await;
+        assert_eq!(sent.message, outgoing(10, "Three", None));
+        assert!(matches!(sent.status, OutboxStatus::Sent(_)));
+
+        assert_eq!(
+            remote.sent(),
+            vec![outgoing(11, "One", None), outgoing(10, "Three", None)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_slow_sends() {
+        let (tx, mut rx, remote) = start_fake();
+        login(&tx, &mut rx).await;
+        remote.set_send_latency(REQUEST_TIMEOUT + Duration::from_secs(1));
+
+        // Answered once queued, long before the GUI would give up on the request
+        let id = send(&tx, DiscordRequest::MessageSend(outgoing(10, "Slow", None)));
+        assert_eq!(expect_outbox(&mut rx).await.status, OutboxStatus::Pending);
+        timeout(Duration::from_secs(1), expect_done(&mut rx, id))
+            .await
+            .unwrap();
+
+        // Other requests don't wait for the message to be sent
+        let id = send(&tx, DiscordRequest::GetGuilds);
+        assert!(matches!(
+            timeout(Duration::from_secs(1), expect_response(&mut rx, id))
+                .await
+                .unwrap(),
+            Ok(DiscordResponse::GuildsListed(_))
+        ));
+        assert!(remote.sent().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_webhooks() {
+        let (tx, mut rx, remote) = start_fake();
+        login(&tx, &mut rx).await;
+
+        let mut msg = outgoing(20, "Hi", None);
+        msg.webhook = Some(WebhookIdentity {
+            name: "Wolfyxon".to_string(),
+            avatar_url: None,
+        });
+
+        let id = send(&tx, DiscordRequest::MessageSend(msg.clone()));
+
+        assert_eq!(expect_outbox(&mut rx).await.status, OutboxStatus::Pending);
+        expect_done(&mut rx, id).await;
+        assert!(matches!(
+            expect_outbox(&mut rx).await.status,
+            OutboxStatus::Sent(_)
+        ));
+        assert_eq!(remote.sent(), vec![msg.clone()]);
+
+        msg.channel_id = 23;
+        let id = send(&tx, DiscordRequest::MessageSend(msg));
+
+        expect_outbox(&mut rx).await;
+        expect_done(&mut rx, id).await;
+        assert!(matches!(
+            expect_outbox(&mut rx).await.status,
+            OutboxStatus::Failed(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_history_paging() {
+        let (tx, mut rx, _remote) = start_fake();
+        login(&tx, &mut rx).await;
+
+        let id = send(&tx, DiscordRequest::GetHistory(20, None));
+
+        let oldest = match expect_response(&mut rx, id).await {
+            Ok(DiscordResponse::HistoryLoaded(20, messages)) => {
+                assert_eq!(messages.len(), discord::HISTORY_PAGE_SIZE as usize);
+                assert!(messages.windows(2).all(|w| w[0].id < w[1].id));
+                assert_eq!(messages.last().unwrap().id, 120);
+
+                messages[0].id
+            }
+            other => panic!("Unexpected response: {:?}", other),
+        };
+
+        let id = send(&tx, DiscordRequest::GetHistory(20, Some(oldest)));
+
+        match expect_response(&mut rx, id).await {
+            Ok(DiscordResponse::HistoryLoaded(20, messages)) => {
+                assert_eq!(messages.last().unwrap().id, oldest - 1);
+            }
+            other => panic!("Unexpected response: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reactions() {
+        let (tx, mut rx, _remote) = start_fake();
+        login(&tx, &mut rx).await;
+
+        let id = send(&tx, DiscordRequest::ReactionAdd(20, 120, "👍".to_string()));
+
+        match rx.recv().await {
+            Some(DiscordCommEvent::ReactionAdded(update)) => {
+                assert_eq!(update.message_id, 120);
+                assert_eq!(update.emoji, "👍");
+                assert!(update.me);
+            }
+            other => panic!("Unexpected event: {:?}", other),
+        }
+
+        expect_done(&mut rx, id).await;
+
+        let id = send(
+            &tx,
+            DiscordRequest::ReactionRemove(20, 120, "🎉".to_string()),
+        );
+        assert!(matches!(
+            expect_response(&mut rx, id).await,
+            Err(ChatError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_attachments() {
+        let (tx, mut rx, remote) = start_fake();
+        login(&tx, &mut rx).await;
+
+        let mut msg = outgoing(20, "Look", None);
+        msg.attachments.push(PathBuf::from("dove.png"));
+
+        let id = send(&tx, DiscordRequest::MessageSend(msg.clone()));
+
+        expect_outbox(&mut rx).await;
+        expect_done(&mut rx, id).await;
+        expect_outbox(&mut rx).await;
+
+        let attachment = AttachmentInfo {
+            id: 5,
+            filename: "dove.png".to_string(),
+            size: 3,
+            url: "https://cdn.example/dove.png".to_string(),
+        };
+
+        let id = send(&tx, DiscordRequest::DownloadAttachment(attachment.clone()));
+
+        match expect_response(&mut rx, id).await {
+            Ok(DiscordResponse::AttachmentDownloaded(downloaded, data)) => {
+                assert_eq!(downloaded, attachment);
+                assert_eq!(data, b"png".to_vec());
+            }
+            other => panic!("Unexpected response: {:?}", other),
+        }
+
+        assert_eq!(remote.sent(), vec![msg]);
+    }
+
+    #[tokio::test]
+    async fn test_direct_messages() {
+        let (tx, mut rx, remote) = start_fake();
+        login(&tx, &mut rx).await;
+
+        let parts = vec!["Hi".to_string(), "there".to_string()];
+        let id = send(
+            &tx,
+            DiscordRequest::DirectMessageSend("wolfyxon".to_string(), parts),
+        );
+
+        // Every part is queued like any other message
+        assert_eq!(expect_outbox(&mut rx).await.status, OutboxStatus::Pending);
+        assert_eq!(expect_outbox(&mut rx).await.status, OutboxStatus::Pending);
+
+        let channel_id = match expect_response(&mut rx, id).await {
+            Ok(DiscordResponse::DirectMessageSent(conversation)) => {
+                assert_eq!(conversation.user.id, 3);
+                conversation.channel_id
+            }
+            other => panic!("Unexpected response: {:?}", other),
+        };
+
+        for _ in 0..2 {
+            assert!(matches!(
+                expect_outbox(&mut rx).await.status,
+                OutboxStatus::Sent(_)
+            ));
+        }
+
+        assert_eq!(
+            remote.sent(),
+            vec![
+                outgoing(channel_id, "Hi", None),
+                outgoing(channel_id, "there", None)
+            ]
+        );
+
+        let id = send(
+            &tx,
+            DiscordRequest::DirectMessageSend("nobody".to_string(), vec!["Hi".to_string()]),
+        );
+        assert!(matches!(
+            expect_response(&mut rx, id).await,
+            Err(ChatError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_typing() {
+        let (tx, mut rx, remote) = start_fake();
+        login(&tx, &mut rx).await;
+
+        let first = send(&tx, DiscordRequest::TypingSend(10));
+        let second = send(&tx, DiscordRequest::TypingSend(99));
+
+        expect_done(&mut rx, first).await;
+        assert!(expect_response(&mut rx, second).await.is_err());
+        assert_eq!(remote.typing(), vec![10]);
+    }
+
+    #[tokio::test]
+    async fn test_mention_candidates() {
+        let (tx, mut rx, _) = start_fake();
+        login(&tx, &mut rx).await;
+
+        let id = send(&tx, DiscordRequest::GetMentionCandidates(10));
+
+        match expect_response(&mut rx, id).await {
+            Ok(DiscordResponse::MentionCandidatesListed(10, candidates)) => {
+                let mentions: Vec<Mention> = candidates.iter().map(|c| c.mention).collect();
+                assert_eq!(
+                    mentions,
+                    vec![Mention::User(3), Mention::Channel(10), Mention::Channel(11)]
+                );
+            }
+            other => panic!("Unexpected response: {:?}", other),
+        }
+    }
+}
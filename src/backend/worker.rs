@@ -0,0 +1,223 @@
+use std::sync::Arc;
+
+use tokio::sync::{
+    Mutex,
+    mpsc::{Sender, UnboundedReceiver},
+};
+
+use crate::{
+    accounts::DEFAULT_ACCOUNT,
+    backend::MessageSender,
+    config,
+    discord::{ChatError, DiscordCommEvent, OutgoingMessage},
+    outbox::{Outbox, OutboxStatus},
+};
+
+/// Outbox of the account in use. The dispatcher changes it in the order of the requests,
+/// the [`OutboxWorker`] sends its pending messages.
+pub struct OutboxState {
+    tx: Sender<DiscordCommEvent>,
+    outbox: Outbox,
+    save: bool,
+    /// Account whose outbox is used
+    account: String,
+}
+
+impl OutboxState {
+    pub fn new(tx: Sender<DiscordCommEvent>, save: bool) -> Self {
+        Self {
+            tx,
+            outbox: Outbox::default(),
+            save,
+            account: DEFAULT_ACCOUNT.to_string(),
+        }
+    }
+
+    /// Replaces the outbox with the saved one of the account and shows its messages.
+    pub async fn switch_account(&mut self, account: String) {
+        self.account = account;
+        self.outbox = Outbox::default();
+
+        if self.save && config::get_outbox_file_path(&self.account).exists() {
+            match config::get_outbox(&self.account) {
+                Ok(outbox) => self.outbox = outbox,
+                Err(e) => eprintln!("Unable to load unsent messages: {}", e),
+            }
+        }
+
+        for entry in self.outbox.entries().to_vec() {
+            self.send_to_gui(DiscordCommEvent::OutboxUpdated(entry))
+                .await;
+        }
+    }
+
+    pub async fn push(&mut self, message: OutgoingMessage) {
+        let entry = self.outbox.push(message);
+
+        self.changed().await;
+        self.send_to_gui(DiscordCommEvent::OutboxUpdated(entry))
+            .await;
+    }
+
+    pub async fn set_status(&mut self, id: u64, status: OutboxStatus) {
+        let Some(entry) = self.outbox.set_status(id, status) else {
+            return;
+        };
+
+        self.changed().await;
+        self.send_to_gui(DiscordCommEvent::OutboxUpdated(entry))
+            .await;
+    }
+
+    pub async fn remove(&mut self, id: u64) -> Result<(), ChatError> {
+        self.outbox.remove(id).ok_or(ChatError::NotFound(
+            "This message is no longer queued".to_string(),
+        ))?;
+
+        self.changed().await;
+        self.send_to_gui(DiscordCommEvent::OutboxDiscarded(id))
+            .await;
+        Ok(())
+    }
+
+    /// Returns the message if it's still waiting to be sent.
+    fn pending(&self, id: u64) -> Option<OutgoingMessage> {
+        self.outbox
+            .get(id)
+            .filter(|entry| entry.status == OutboxStatus::Pending)
+            .map(|entry| entry.message.clone())
+    }
+
+    async fn changed(&mut self) {
+        if !self.save {
+            return;
+        }
+
+        if let Err(e) = config::save_outbox(&self.account, &self.outbox) {
+            let e = ChatError::Internal(format!("Unable to save unsent messages: {}", e));
+            self.send_to_gui(DiscordCommEvent::Error(e)).await;
+        }
+    }
+
+    async fn send_to_gui(&self, event: DiscordCommEvent) {
+        self.tx.send(event).await.unwrap_or_else(|err| {
+            eprintln!("Failed to send OutboxState -> App: {:?}", err);
+        });
+    }
+}
+
+/// Sends the pending messages of the outbox in a task of its own, so waiting for slowmode or
+/// rate limits doesn't hold up other requests.
+pub struct OutboxWorker<S: MessageSender> {
+    /// Locked for each send, so logging out waits for the message being sent
+    sender: Arc<Mutex<S>>,
+    outbox: Arc<Mutex<OutboxState>>,
+    tx: Sender<DiscordCommEvent>,
+    /// Asks for the outbox to be sent
+    flushes: UnboundedReceiver<()>,
+}
+
+impl<S: MessageSender> OutboxWorker<S> {
+    pub fn new(
+        sender: Arc<Mutex<S>>,
+        outbox: Arc<Mutex<OutboxState>>,
+        tx: Sender<DiscordCommEvent>,
+        flushes: UnboundedReceiver<()>,
+    ) -> Self {
+        Self {
+            sender,
+            outbox,
+            tx,
+            flushes,
+        }
+    }
+
+    /// Sends pending messages in order. Stops when offline, they stay queued until the next flush.
+    /// Channels that have to wait are skipped, keeping the order within each channel.
+    async fn flush(&mut self) {
+        let mut delayed: Vec<u64> = Vec::new();
+
+        let (account, ids) = {
+            let state = self.outbox.lock().await;
+            (state.account.clone(), state.outbox.pending_ids())
+        };
+
+        for id in ids {
+            let mut sender = self.sender.lock().await;
+
+            if !sender.is_ready().await {
+                return;
+            }
+
+            let message = {
+                let state = self.outbox.lock().await;
+
+                // Another account logged in since the flush started
+                if state.account != account {
+                    return;
+                }
+
+                match state.pending(id) {
+                    Some(message) => message,
+                    None => continue,
+                }
+            };
+
+            let channel_id = message.channel_id;
+
+            if delayed.contains(&channel_id) {
+                continue;
+            }
+
+            // The GUI flushes again once the delay is over
+            if let Some(delay) = sender.send_delay(channel_id).await {
+                delayed.push(channel_id);
+                self.send_to_gui(DiscordCommEvent::SendDelayed(Some(channel_id), delay))
+                    .await;
+                continue;
+            }
+
+            let status = match sender.send_message(message).await {
+                Ok(message_id) => OutboxStatus::Sent(message_id),
+                Err(ChatError::RateLimited(Some(delay))) => {
+                    delayed.push(channel_id);
+                    self.send_to_gui(DiscordCommEvent::SendDelayed(Some(channel_id), delay))
+                        .await;
+                    continue;
+                }
+                // Worth retrying once the connection or the rate limit recovers
+                Err(ChatError::Auth(_) | ChatError::Network(_) | ChatError::RateLimited(_)) => {
+                    return;
+                }
+                // The connection dropped while sending
+                Err(_) if !sender.is_ready().await => return,
+                Err(e) => OutboxStatus::Failed(e.to_string()),
+            };
+
+            let sent = matches!(status, OutboxStatus::Sent(_));
+            self.outbox.lock().await.set_status(id, status).await;
+
+            // Lets the GUI count down the slowmode
+            if sent && let Some(delay) = sender.send_delay(channel_id).await {
+                self.send_to_gui(DiscordCommEvent::SendDelayed(Some(channel_id), delay))
+                    .await;
+            }
+        }
+    }
+
+    async fn send_to_gui(&self, event: DiscordCommEvent) {
+        self.tx.send(event).await.unwrap_or_else(|err| {
+            eprintln!("Failed to send OutboxWorker -> App: {:?}", err);
+        });
+    }
+
+    /// Flushes the outbox whenever asked to, until the dispatcher stops.
+    pub async fn start(mut self) {
+        while self.flushes.recv().await.is_some() {
+            // Messages queued meanwhile are sent by this flush too
+            while self.flushes.try_recv().is_ok() {}
+
+            self.flush().await;
+        }
+    }
+}
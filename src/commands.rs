@@ -4,6 +4,8 @@ pub const COMMAND_PREFIX: &str = "/";
 
 pub struct CommandContext {
     pub args: Vec<String>,
+    /// Everything after the command name, with its spacing kept
+    pub raw_args: String,
 }
 
 #[derive(Clone)]
@@ -22,6 +24,11 @@ impl ChatCommand {
         }
     }
 
+    pub fn with_alias(&mut self, alias: impl Into<String>) -> Self {
+        self.aliases.push(alias.into());
+        self.to_owned()
+    }
+
     pub fn with_handler(&mut self, handler: fn(&mut App, CommandContext) -> ()) -> Self {
         self.handler = handler;
         self.to_owned()
@@ -0,0 +1,129 @@
+use crate::discord::{Mention, MentionCandidate};
+
+pub const MAX_SUGGESTIONS: usize = 8;
+
+/// Mention being typed at the end of the input, e.g. `@wol` or `#gen`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompletionQuery<'a> {
+    /// Byte index of the `@` or `#`
+    pub start: usize,
+    pub trigger: char,
+    pub text: &'a str,
+}
+
+impl<'a> CompletionQuery<'a> {
+    pub fn find(input: &'a str) -> Option<Self> {
+        let start = input
+            .rfind(char::is_whitespace)
+            .map(|idx| idx + input[idx..].chars().next().map_or(1, char::len_utf8))
+            .unwrap_or(0);
+
+        let word = &input[start..];
+        let trigger = word.chars().next()?;
+
+        if trigger != '@' && trigger != '#' {
+            return None;
+        }
+
+        Some(Self {
+            start,
+            trigger,
+            text: &word[1..],
+        })
+    }
+
+    fn accepts(&self, mention: &Mention) -> bool {
+        matches!(
+            (self.trigger, mention),
+            ('@', Mention::User(_)) | ('#', Mention::Channel(_))
+        )
+    }
+
+    /// Returns matching candidates, names starting with the query first.
+    pub fn filter<'c>(&self, candidates: &'c [MentionCandidate]) -> Vec<&'c MentionCandidate> {
+        let query = self.text.to_lowercase();
+
+        let mut matches: Vec<(bool, &MentionCandidate)> = candidates
+            .iter()
+            .filter(|c| self.accepts(&c.mention))
+            .filter_map(|c| {
+                let name = c.name.to_lowercase();
+                name.contains(&query)
+                    .then(|| (!name.starts_with(&query), c))
+            })
+            .collect();
+
+        matches.sort_by(|(a_later, a), (b_later, b)| {
+            a_later
+                .cmp(b_later)
+                .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+        });
+
+        matches
+            .into_iter()
+            .take(MAX_SUGGESTIONS)
+            .map(|(_, c)| c)
+            .collect()
+    }
+
+    /// Replaces the query in `input` with the candidate's markup.
+    pub fn apply(&self, input: &str, candidate: &MentionCandidate) -> String {
+        format!("{}{} ", &input[..self.start], candidate.mention.markup())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        completion::CompletionQuery,
+        discord::{Mention, MentionCandidate},
+    };
+
+    fn candidates() -> Vec<MentionCandidate> {
+        vec![
+            MentionCandidate {
+                mention: Mention::User(1),
+                name: "Wolfyxon".to_string(),
+            },
+            MentionCandidate {
+                mention: Mention::User(2),
+                name: "Owl".to_string(),
+            },
+            MentionCandidate {
+                mention: Mention::Channel(3),
+                name: "general".to_string(),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_find() {
+        let query = CompletionQuery::find("hi @wo").unwrap();
+        assert_eq!(query.start, 3);
+        assert_eq!(query.trigger, '@');
+        assert_eq!(query.text, "wo");
+
+        assert_eq!(CompletionQuery::find("#").unwrap().text, "");
+        assert!(CompletionQuery::find("hi @wo ").is_none());
+        assert!(CompletionQuery::find("mail@example").is_none());
+        assert!(CompletionQuery::find("").is_none());
+    }
+
+    #[test]
+    fn test_filter_and_apply() {
+        let candidates = candidates();
+
+        let query = CompletionQuery::find("hi @o").unwrap();
+        let names: Vec<&str> = query
+            .filter(&candidates)
+            .iter()
+            .map(|c| c.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["Owl", "Wolfyxon"]);
+
+        let query = CompletionQuery::find("see #gen").unwrap();
+        let matches = query.filter(&candidates);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(query.apply("see #gen", matches[0]), "see <#3> ");
+    }
+}
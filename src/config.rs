@@ -5,12 +5,21 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use crate::crypto;
+use crate::{
+    accounts::{Accounts, DEFAULT_ACCOUNT},
+    crypto,
+    discord::{ChannelInfo, ChannelKind, ChannelPermissions},
+    identity::Identity,
+    outbox::Outbox,
+    subscriptions::Subscriptions,
+    webhooks::WebhookSettings,
+};
 
 #[derive(Debug)]
 pub enum Error {
     Io(io::Error),
     Aes256(crypto::aes256::Error),
+    InvalidData(String),
 }
 
 impl std::error::Error for Error {}
@@ -20,6 +29,7 @@ impl Display for Error {
         let res = match &self {
             Self::Io(e) => e.to_string(),
             Self::Aes256(e) => e.to_string(),
+            Self::InvalidData(e) => e.to_owned(),
         };
 
         write!(f, "{}", res)
@@ -32,20 +42,55 @@ pub fn get_dir() -> PathBuf {
         .unwrap_or(Path::new("dove").to_path_buf())
 }
 
-pub fn create_dir() -> Result<(), Error> {
-    std::fs::create_dir_all(get_dir()).map_err(|e| Error::Io(e))
+/// Directory with the token and settings of an account.
+pub fn get_account_dir(account: &str) -> PathBuf {
+    match account {
+        "" | DEFAULT_ACCOUNT => get_dir(),
+        account => get_dir().join("accounts").join(account),
+    }
+}
+
+pub fn create_dir(account: &str) -> Result<(), Error> {
+    std::fs::create_dir_all(get_account_dir(account)).map_err(|e| Error::Io(e))
+}
+
+/// Deletes the token and settings of an account. The default account only loses its token.
+pub fn delete_account(name: &str) -> Result<(), io::Error> {
+    match name {
+        DEFAULT_ACCOUNT => fs::remove_file(get_account_dir(name).join("tk.dat")),
+        name => fs::remove_dir_all(get_account_dir(name)),
+    }
+}
+
+pub fn get_accounts_file_path() -> PathBuf {
+    get_dir().join("accounts.txt")
+}
+
+pub fn get_accounts() -> Result<Accounts, Error> {
+    let text = fs::read_to_string(get_accounts_file_path()).map_err(Error::Io)?;
+
+    Ok(Accounts::parse(&text))
+}
+
+pub fn save_accounts(accounts: &Accounts) -> Result<(), Error> {
+    fs::create_dir_all(get_dir()).map_err(Error::Io)?;
+    fs::write(get_accounts_file_path(), accounts.serialize()).map_err(Error::Io)
+}
+
+pub fn has_saved_token(account: &str) -> bool {
+    get_token_file_path(account).exists()
 }
 
-pub fn get_token_file_path() -> PathBuf {
-    get_dir().join("tk.dat")
+pub fn get_token_file_path(account: &str) -> PathBuf {
+    get_account_dir(account).join("tk.dat")
 }
 
-pub fn delete_token_file() -> Result<(), io::Error> {
-    fs::remove_file(get_token_file_path())
+pub fn delete_token_file(account: &str) -> Result<(), io::Error> {
+    fs::remove_file(get_token_file_path(account))
 }
 
-fn get_encrypted_token() -> Result<Vec<u8>, io::Error> {
-    let mut file = File::open(get_token_file_path())?;
+fn get_encrypted_token(account: &str) -> Result<Vec<u8>, io::Error> {
+    let mut file = File::open(get_token_file_path(account))?;
     let mut buf: Vec<u8> = Vec::new();
 
     file.read_to_end(&mut buf)?;
@@ -53,19 +98,148 @@ fn get_encrypted_token() -> Result<Vec<u8>, io::Error> {
     Ok(buf)
 }
 
-pub fn get_token() -> Result<String, Error> {
-    let encrypted = get_encrypted_token().map_err(|e| Error::Io(e))?;
+pub fn get_token(account: &str) -> Result<String, Error> {
+    let encrypted = get_encrypted_token(account).map_err(|e| Error::Io(e))?;
     crypto::aes256::decrypt_string(encrypted).map_err(|e| Error::Aes256(e))
 }
 
-fn save_encrypted_token(buf: &mut Vec<u8>) -> Result<(), Error> {
-    let mut file = File::create(get_token_file_path()).map_err(|e| Error::Io(e))?;
+fn save_encrypted_token(account: &str, buf: &mut Vec<u8>) -> Result<(), Error> {
+    let mut file = File::create(get_token_file_path(account)).map_err(|e| Error::Io(e))?;
     file.write_all(buf).map_err(|e| Error::Io(e))
 }
 
-pub fn save_token(token: String) -> Result<(), Error> {
-    create_dir()?;
+pub fn save_token(account: &str, token: String) -> Result<(), Error> {
+    create_dir(account)?;
     let mut encrypted = crypto::aes256::encrypt_string(token).map_err(|e| Error::Aes256(e))?;
 
-    save_encrypted_token(&mut encrypted)
+    save_encrypted_token(account, &mut encrypted)
+}
+
+pub fn get_active_channel_file_path(account: &str) -> PathBuf {
+    get_account_dir(account).join("channel.txt")
+}
+
+pub fn get_active_channel(account: &str) -> Result<ChannelInfo, Error> {
+    let text = fs::read_to_string(get_active_channel_file_path(account)).map_err(Error::Io)?;
+    let mut lines = text.lines();
+
+    let id = lines
+        .next()
+        .and_then(|line| line.trim().parse::<u64>().ok())
+        .ok_or(Error::InvalidData("Invalid channel ID".to_string()))?;
+
+    Ok(ChannelInfo {
+        id,
+        guild_name: lines.next().unwrap_or_default().to_string(),
+        name: lines.next().unwrap_or_default().to_string(),
+        kind: lines
+            .next()
+            .and_then(ChannelKind::from_label)
+            .unwrap_or(ChannelKind::Text),
+        parent_id: None,
+        permissions: ChannelPermissions::default(),
+    })
+}
+
+pub fn save_active_channel(account: &str, channel: &ChannelInfo) -> Result<(), Error> {
+    create_dir(account)?;
+
+    let text = format!(
+        "{}\n{}\n{}\n{}\n",
+        channel.id,
+        channel.guild_name,
+        channel.name,
+        channel.kind.label()
+    );
+    fs::write(get_active_channel_file_path(account), text).map_err(Error::Io)
+}
+
+pub fn get_subscriptions_file_path(account: &str) -> PathBuf {
+    get_account_dir(account).join("watched.txt")
+}
+
+pub fn get_subscriptions(account: &str) -> Result<Subscriptions, Error> {
+    let text = fs::read_to_string(get_subscriptions_file_path(account)).map_err(Error::Io)?;
+
+    Ok(Subscriptions::parse(&text))
+}
+
+pub fn save_subscriptions(account: &str, subscriptions: &Subscriptions) -> Result<(), Error> {
+    create_dir(account)?;
+    fs::write(
+        get_subscriptions_file_path(account),
+        subscriptions.serialize(),
+    )
+    .map_err(Error::Io)
+}
+
+pub fn get_outbox_file_path(account: &str) -> PathBuf {
+    get_account_dir(account).join("outbox.txt")
+}
+
+pub fn get_outbox(account: &str) -> Result<Outbox, Error> {
+    let text = fs::read_to_string(get_outbox_file_path(account)).map_err(Error::Io)?;
+
+    Ok(Outbox::parse(&text))
+}
+
+pub fn save_outbox(account: &str, outbox: &Outbox) -> Result<(), Error> {
+    create_dir(account)?;
+    fs::write(get_outbox_file_path(account), outbox.serialize()).map_err(Error::Io)
+}
+
+pub fn get_identity_file_path(account: &str) -> PathBuf {
+    get_account_dir(account).join("identity.txt")
+}
+
+pub fn get_identity(account: &str) -> Result<Identity, Error> {
+    let text = fs::read_to_string(get_identity_file_path(account)).map_err(Error::Io)?;
+
+    Ok(Identity::parse(&text))
+}
+
+pub fn save_identity(account: &str, identity: &Identity) -> Result<(), Error> {
+    create_dir(account)?;
+    fs::write(get_identity_file_path(account), identity.serialize()).map_err(Error::Io)
+}
+
+pub fn get_webhooks_file_path(account: &str) -> PathBuf {
+    get_account_dir(account).join("webhooks.txt")
+}
+
+pub fn get_webhook_settings(account: &str) -> Result<WebhookSettings, Error> {
+    let text = fs::read_to_string(get_webhooks_file_path(account)).map_err(Error::Io)?;
+
+    Ok(WebhookSettings::parse(&text))
+}
+
+pub fn save_webhook_settings(account: &str, settings: &WebhookSettings) -> Result<(), Error> {
+    create_dir(account)?;
+    fs::write(get_webhooks_file_path(account), settings.serialize()).map_err(Error::Io)
+}
+
+pub fn get_downloads_dir() -> PathBuf {
+    get_dir().join("downloads")
+}
+
+/// Saves a downloaded file without overwriting existing ones and returns its path.
+pub fn save_download(id: u64, filename: &str, data: &[u8]) -> Result<PathBuf, Error> {
+    let dir = get_downloads_dir();
+    fs::create_dir_all(&dir).map_err(Error::Io)?;
+
+    // Never trust the name to not contain a path
+    let filename = Path::new(filename)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or(id.to_string());
+
+    let mut path = dir.join(&filename);
+
+    if path.exists() {
+        path = dir.join(format!("{}-{}", id, filename));
+    }
+
+    fs::write(&path, data).map_err(Error::Io)?;
+
+    Ok(path)
 }
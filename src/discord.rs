@@ -1,104 +1,833 @@
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    num::NonZeroU64,
+    path::PathBuf,
+    sync::{Arc, LazyLock},
+    time::{Duration, Instant},
+};
 
+use regex::{Captures, Regex};
 use serenity::{
     Client,
     all::{
-        Cache, ChannelId, ChannelType, Context, EventHandler, GatewayError, GatewayIntents,
-        GuildChannel, GuildId, GuildInfo, Http, Message, Ready, ShardManager,
+        Cache, ChannelId, ChannelType, Context, CreateAttachment, CreateMessage, CreateWebhook,
+        Embed, EventHandler, ExecuteWebhook, GatewayError, GatewayIntents, GetMessages,
+        GuildChannel, GuildId, Http, HttpError, Member, Message, MessageId, MessageUpdateEvent,
+        ModelError, PartialGuild, Permissions, Reaction, ReactionType, Ready, RoleId, ShardManager,
+        TypingStartEvent, User, UserId, Webhook,
     },
     async_trait,
+    http::{LightMethod, RatelimitInfo},
 };
 use tokio::{
-    sync::{
-        Mutex,
-        mpsc::{Receiver, Sender},
-    },
+    sync::{Mutex, mpsc::Sender},
     task::JoinHandle,
 };
 
-pub type DiscordMessage = serenity::all::Message;
+use crate::{
+    backend::{ChatBackend, MessageSender},
+    outbox::OutboxEntry,
+    ratelimit::SendPacer,
+};
 
-#[derive(Debug)]
-pub enum DiscordCommEvent {
-    // GUI -> Discord
+/// GUI -> Discord
+#[derive(Debug, Clone)]
+pub enum DiscordRequest {
     Login(String),
     Logout,
-    MessageSend(u64, String),
+    /// Uses the outbox of another account, sent after logging out
+    SwitchAccount(String),
+    MessageSend(OutgoingMessage),
     GetGuilds,
     GetAvailableTextChannels(u64),
-    // Discord -> GUI
-    Ready,
-    Error(String),
-    MessageReceived(DiscordMessage),
-    GuildsListed(Vec<GuildInfo>),
-    AvailableTextChannelsListed(Vec<GuildChannel>),
+    JoinChannel(u64),
+    JoinChannelByName(String, String),
+    /// Gets the current permissions in a channel that is already joined
+    RefreshChannel(u64),
+    GetHistory(u64, Option<u64>),
+    ReactionAdd(u64, u64, String),
+    ReactionRemove(u64, u64, String),
+    DownloadAttachment(AttachmentInfo),
+    /// Queues the parts of a message to a user found by ID or name
+    DirectMessageSend(String, Vec<String>),
+    TypingSend(u64),
+    GetMentionCandidates(u64),
+    OutboxFlush,
+    OutboxRetry(u64),
+    OutboxDiscard(u64),
+}
+
+/// Request with an ID that its response will carry.
+#[derive(Debug, Clone)]
+pub struct RequestEnvelope {
+    pub id: u64,
+    pub request: DiscordRequest,
+}
+
+/// Result of a successful request.
+#[derive(Debug)]
+pub enum DiscordResponse {
+    /// The request has no result besides succeeding
+    Done,
+    GuildsListed(Vec<GuildSummary>),
+    AvailableTextChannelsListed(Vec<ChannelInfo>),
+    ChannelJoined(ChannelInfo),
+    ChannelRefreshed(ChannelInfo),
+    HistoryLoaded(u64, Vec<ChatMessage>),
+    AttachmentDownloaded(AttachmentInfo, Vec<u8>),
+    DirectMessageSent(DirectConversation),
+    MentionCandidatesListed(u64, Vec<MentionCandidate>),
+}
+
+/// Discord -> GUI
+#[derive(Debug)]
+pub enum DiscordCommEvent {
+    /// Sent exactly once for every request, in the order the requests were made
+    Responded(u64, Result<DiscordResponse, ChatError>),
+    /// Failure not caused by a request
+    Error(ChatError),
+    Ready(UserSummary),
+    MessageReceived(ChatMessage),
+    MessageEdited(u64, u64, String),
+    EmbedsUpdated(u64, u64, Vec<EmbedInfo>),
+    MessagesDeleted(u64, Vec<u64>),
+    ReactionAdded(ReactionUpdate),
+    ReactionRemoved(ReactionUpdate),
+    TypingStarted(TypingUpdate),
+    ConnectionStateChanged(ConnectionState),
+    OutboxUpdated(OutboxEntry),
+    OutboxDiscarded(u64),
+    /// Messages to the channel, or all channels if None, can't be sent for the duration
+    SendDelayed(Option<u64>, Duration),
 }
 
 pub const MESSAGE_LEN_LIMIT: usize = 2000;
+pub const HISTORY_PAGE_SIZE: u8 = 50;
+/// How long a typing indicator lasts on Discord.
+pub const TYPING_DURATION: Duration = Duration::from_secs(10);
 
-pub struct DiscordManager {
+const RECONNECT_BASE_DELAY: Duration = Duration::from_secs(1);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(5 * 60);
+
+/// Name of the webhooks Dove creates, messages show the sender's name instead.
+const WEBHOOK_NAME: &str = "Dove";
+
+const DISCORD_ERROR_MISSING_ACCESS: isize = 50001;
+const DISCORD_ERROR_MISSING_PERMISSIONS: isize = 50013;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConnectionState {
+    LoggedOut,
+    Connecting,
+    Ready,
+    /// Waiting `delay` before the next attempt after the connection was lost
+    Reconnecting {
+        attempt: u32,
+        delay: Duration,
+        reason: String,
+    },
+    /// Retrying won't help, e.g. the token is invalid
+    Failed(String),
+}
+
+/// Why a request failed, so the GUI can tell the user what to do about it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChatError {
+    /// Not logged in or the token was rejected
+    Auth(String),
+    Permission(String),
+    /// Discord asked to slow down, retry after the given time if known
+    RateLimited(Option<Duration>),
+    NotFound(String),
+    /// The request can't work as given, e.g. an unknown emoji or an unreadable file
+    InvalidInput(String),
+    Network(String),
+    Internal(String),
+}
+
+impl ChatError {
+    pub fn not_logged_in() -> Self {
+        Self::Auth("Not logged in".to_string())
+    }
+
+    /// Classifies a failed request. `action` completes "Unable to ...", `permission` names
+    /// the Discord permission the request needs, if it's worth mentioning.
+    fn from_serenity(e: serenity::Error, action: &str, permission: Option<&str>) -> Self {
+        let res = match &e {
+            serenity::Error::Http(HttpError::UnsuccessfulRequest(res)) => res,
+            serenity::Error::Http(HttpError::Request(_))
+            | serenity::Error::Io(_)
+            | serenity::Error::Gateway(_)
+            | serenity::Error::Tungstenite(_) => {
+                return Self::Network(format!("Unable to {}: {}", action, e));
+            }
+            serenity::Error::Model(ModelError::InvalidPermissions { .. }) => {
+                return Self::Permission(format!("Unable to {}: {}", action, e));
+            }
+            _ => return Self::Internal(format!("Unable to {}: {}", action, e)),
+        };
+
+        match (res.error.code, permission) {
+            (DISCORD_ERROR_MISSING_ACCESS, _) => {
+                return Self::Permission(format!(
+                    "Unable to {}: the bot has no access to this channel",
+                    action
+                ));
+            }
+            (DISCORD_ERROR_MISSING_PERMISSIONS, Some(permission)) => {
+                return Self::Permission(format!(
+                    "Unable to {}: the bot is missing the {} permission here. Ask a server admin to grant it",
+                    action, permission
+                ));
+            }
+            _ => (),
+        }
+
+        let text = format!("Unable to {}: {}", action, e);
+
+        match res.status_code.as_u16() {
+            401 => Self::Auth(text),
+            403 => Self::Permission(text),
+            404 => Self::NotFound(text),
+            // 429 never gets here, serenity waits out rate limits and retries by itself.
+            // They are reported through `EventHandler::ratelimit` instead.
+            500.. => Self::Network(text),
+            _ => Self::Internal(text),
+        }
+    }
+}
+
+impl std::error::Error for ChatError {}
+
+impl Display for ChatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let res = match &self {
+            Self::RateLimited(Some(retry_after)) => format!(
+                "Rate limited, try again in {}s",
+                retry_after.as_secs_f32().ceil()
+            ),
+            Self::RateLimited(None) => "Rate limited, try again later".to_string(),
+            Self::Auth(e)
+            | Self::Permission(e)
+            | Self::NotFound(e)
+            | Self::InvalidInput(e)
+            | Self::Network(e)
+            | Self::Internal(e) => e.to_owned(),
+        };
+
+        write!(f, "{}", res)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GuildSummary {
+    pub id: u64,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserSummary {
+    pub id: u64,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DirectConversation {
+    pub channel_id: u64,
+    pub user: UserSummary,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChannelKind {
+    Text,
+    Announcement,
+    Voice,
+    Stage,
+    Forum,
+    Thread,
+}
+
+impl ChannelKind {
+    /// Returns None for channels that can't hold messages, like categories.
+    fn from_channel_type(kind: ChannelType) -> Option<Self> {
+        match kind {
+            ChannelType::Text => Some(Self::Text),
+            ChannelType::News => Some(Self::Announcement),
+            ChannelType::Voice => Some(Self::Voice),
+            ChannelType::Stage => Some(Self::Stage),
+            ChannelType::Forum => Some(Self::Forum),
+            ChannelType::PublicThread | ChannelType::PrivateThread | ChannelType::NewsThread => {
+                Some(Self::Thread)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Text => "text",
+            Self::Announcement => "announcement",
+            Self::Voice => "voice",
+            Self::Stage => "stage",
+            Self::Forum => "forum",
+            Self::Thread => "thread",
+        }
+    }
+
+    pub fn from_label(label: &str) -> Option<Self> {
+        [
+            Self::Text,
+            Self::Announcement,
+            Self::Voice,
+            Self::Stage,
+            Self::Forum,
+            Self::Thread,
+        ]
+        .into_iter()
+        .find(|kind| kind.label() == label)
+    }
+}
+
+/// What the bot is allowed to do in a channel it can view.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChannelPermissions {
+    pub send: bool,
+    pub attach: bool,
+    pub read_history: bool,
+    /// Manage Webhooks, needed to send in webhook mode
+    pub webhooks: bool,
+}
+
+impl Default for ChannelPermissions {
+    /// Unknown permissions are assumed to be granted, Discord will refuse the request otherwise.
+    fn default() -> Self {
+        Self {
+            send: true,
+            attach: true,
+            read_history: true,
+            webhooks: true,
+        }
+    }
+}
+
+impl ChannelPermissions {
+    /// Returns None if the bot can't view the channel.
+    /// Threads must be given the permissions of their parent channel.
+    fn from_permissions(permissions: Permissions, thread: bool) -> Option<Self> {
+        if !permissions.view_channel() {
+            return None;
+        }
+
+        let send = if thread {
+            permissions.send_messages_in_threads()
+        } else {
+            permissions.send_messages()
+        };
+
+        Some(Self {
+            send,
+            attach: permissions.attach_files(),
+            read_history: permissions.read_message_history(),
+            webhooks: permissions.manage_webhooks(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChannelInfo {
+    pub id: u64,
+    pub name: String,
+    pub guild_name: String,
+    pub kind: ChannelKind,
+    /// The channel a thread or forum post belongs to
+    pub parent_id: Option<u64>,
+    pub permissions: ChannelPermissions,
+}
+
+impl ChannelInfo {
+    fn from_guild_channel(
+        channel: &GuildChannel,
+        guild_name: impl Into<String>,
+        permissions: ChannelPermissions,
+    ) -> Option<Self> {
+        let kind = ChannelKind::from_channel_type(channel.kind)?;
+
+        let parent_id = match kind {
+            ChannelKind::Thread => channel.parent_id.map(|id| id.get()),
+            _ => None,
+        };
+
+        Some(Self {
+            id: channel.id.get(),
+            name: channel.name.to_owned(),
+            guild_name: guild_name.into(),
+            kind,
+            parent_id,
+            permissions,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChatMessage {
+    pub id: u64,
+    pub channel_id: u64,
+    pub guild_id: Option<u64>,
+    pub author_id: u64,
+    pub author_name: String,
+    pub content: String,
+    pub edited: bool,
+    pub reply_to: Option<MessageQuote>,
+    pub reactions: Vec<ReactionCount>,
+    pub attachments: Vec<AttachmentInfo>,
+    pub embeds: Vec<EmbedInfo>,
+    pub mentions_me: bool,
+    /// The author is a webhook, its name is the one chosen by the sender
+    pub from_webhook: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmbedInfo {
+    pub author: Option<String>,
+    pub title: Option<String>,
+    pub url: Option<String>,
+    pub description: Option<String>,
+    pub fields: Vec<(String, String)>,
+    pub footer: Option<String>,
+    pub color: Option<u32>,
+}
+
+impl From<&Embed> for EmbedInfo {
+    fn from(embed: &Embed) -> Self {
+        Self {
+            author: embed.author.as_ref().map(|author| author.name.to_owned()),
+            title: embed.title.to_owned(),
+            url: embed.url.to_owned(),
+            description: embed.description.to_owned(),
+            fields: embed
+                .fields
+                .iter()
+                .map(|field| (field.name.to_owned(), field.value.to_owned()))
+                .collect(),
+            footer: embed.footer.as_ref().map(|footer| footer.text.to_owned()),
+            color: embed.colour.map(|colour| colour.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttachmentInfo {
+    pub id: u64,
+    pub filename: String,
+    pub size: u64,
+    pub url: String,
+}
+
+/// Emojis are stored as Discord formats them: unicode as is, custom ones as `<:name:id>`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReactionCount {
+    pub emoji: String,
+    pub count: u64,
+    pub me: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReactionUpdate {
+    pub channel_id: u64,
+    pub message_id: u64,
+    pub emoji: String,
+    pub me: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypingUpdate {
+    pub channel_id: u64,
+    pub user: UserSummary,
+}
+
+/// Short preview of the message that another message replies to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MessageQuote {
+    pub id: u64,
+    pub author_id: u64,
+    pub author_name: String,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutgoingMessage {
+    pub channel_id: u64,
+    pub content: String,
+    pub reply_to: Option<u64>,
+    pub attachments: Vec<PathBuf>,
+    /// Sent through a channel webhook under this identity instead of as the bot
+    pub webhook: Option<WebhookIdentity>,
+}
+
+/// Name and avatar shown on messages sent through a webhook.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WebhookIdentity {
+    pub name: String,
+    pub avatar_url: Option<String>,
+}
+
+impl From<&Message> for ChatMessage {
+    fn from(msg: &Message) -> Self {
+        Self {
+            id: msg.id.get(),
+            channel_id: msg.channel_id.get(),
+            guild_id: msg.guild_id.map(|id| id.get()),
+            author_id: msg.author.id.get(),
+            author_name: msg.author.display_name().to_string(),
+            content: msg.content.to_owned(),
+            edited: msg.edited_timestamp.is_some(),
+            reply_to: msg
+                .referenced_message
+                .as_ref()
+                .map(|referenced| MessageQuote {
+                    id: referenced.id.get(),
+                    author_id: referenced.author.id.get(),
+                    author_name: referenced.author.display_name().to_string(),
+                    content: referenced.content.to_owned(),
+                }),
+            reactions: msg
+                .reactions
+                .iter()
+                .map(|reaction| ReactionCount {
+                    emoji: reaction.reaction_type.to_string(),
+                    count: reaction.count,
+                    me: reaction.me,
+                })
+                .collect(),
+            attachments: msg
+                .attachments
+                .iter()
+                .map(|attachment| AttachmentInfo {
+                    id: attachment.id.get(),
+                    filename: attachment.filename.to_owned(),
+                    size: attachment.size as u64,
+                    url: attachment.url.to_owned(),
+                })
+                .collect(),
+            embeds: msg.embeds.iter().map(EmbedInfo::from).collect(),
+            mentions_me: false,
+            from_webhook: msg.webhook_id.is_some(),
+        }
+    }
+}
+
+impl ChatMessage {
+    /// Converts the message with mentions resolved to names and mentions of the bot detected.
+    fn from_cached(msg: &Message, cache: &Cache) -> Self {
+        let mut chat_msg = Self::from(msg);
+        let bot_id = cache.current_user().id;
+
+        chat_msg.content = resolve_markup(&msg.content, cache, msg.guild_id, &msg.mentions);
+
+        if let Some(quote) = &mut chat_msg.reply_to {
+            quote.content = resolve_markup(&quote.content, cache, msg.guild_id, &msg.mentions);
+        }
+
+        let mentions_role = || {
+            let Some(guild) = msg.guild_id.and_then(|id| cache.guild(id)) else {
+                return false;
+            };
+
+            guild
+                .members
+                .get(&bot_id)
+                .is_some_and(|member| msg.mention_roles.iter().any(|r| member.roles.contains(r)))
+        };
+
+        chat_msg.mentions_me = msg.mentions.iter().any(|user| user.id == bot_id) || mentions_role();
+
+        chat_msg
+    }
+}
+
+/// Target of a mention in message markup.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mention {
+    User(u64),
+    Role(u64),
+    Channel(u64),
+}
+
+impl Mention {
+    pub fn markup(&self) -> String {
+        match self {
+            Mention::User(id) => format!("<@{}>", id),
+            Mention::Role(id) => format!("<@&{}>", id),
+            Mention::Channel(id) => format!("<#{}>", id),
+        }
+    }
+}
+
+/// Member or channel that can be mentioned in a channel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MentionCandidate {
+    pub mention: Mention,
+    pub name: String,
+}
+
+static MARKUP_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"<(@!?|@&|#)(\d+)>|<a?:(\w+):\d+>").expect("Invalid markup regex")
+});
+
+/// Replaces mentions (`<@id>`, `<@&id>`, `<#id>`) with names from `resolve` and custom emoji with `:name:`.
+pub fn replace_markup(content: &str, resolve: impl Fn(Mention) -> Option<String>) -> String {
+    MARKUP_REGEX
+        .replace_all(content, |caps: &Captures| {
+            if let Some(emoji_name) = caps.get(3) {
+                return format!(":{}:", emoji_name.as_str());
+            }
+
+            // Zero isn't a valid ID and can't be looked up
+            let Ok(id) = caps[2].parse::<NonZeroU64>().map(NonZeroU64::get) else {
+                return caps[0].to_string();
+            };
+
+            match &caps[1] {
+                "@&" => format!(
+                    "@{}",
+                    resolve(Mention::Role(id)).unwrap_or("unknown-role".into())
+                ),
+                "#" => format!(
+                    "#{}",
+                    resolve(Mention::Channel(id)).unwrap_or("unknown".into())
+                ),
+                _ => format!(
+                    "@{}",
+                    resolve(Mention::User(id)).unwrap_or("unknown-user".into())
+                ),
+            }
+        })
+        .into_owned()
+}
+
+/// Resolves markup using users mentioned in the message and the cache.
+fn resolve_markup(
+    content: &str,
+    cache: &Cache,
+    guild_id: Option<GuildId>,
+    users: &[User],
+) -> String {
+    replace_markup(content, |mention| match mention {
+        Mention::User(id) => {
+            let user_id = UserId::new(id);
+
+            if let Some(guild) = guild_id.and_then(|id| cache.guild(id))
+                && let Some(member) = guild.members.get(&user_id)
+            {
+                return Some(member.display_name().to_string());
+            }
+
+            match users.iter().find(|user| user.id == user_id) {
+                Some(user) => Some(user.display_name().to_string()),
+                None => cache
+                    .user(user_id)
+                    .map(|user| user.display_name().to_string()),
+            }
+        }
+        Mention::Role(id) => {
+            let guild = cache.guild(guild_id?)?;
+            guild
+                .roles
+                .get(&RoleId::new(id))
+                .map(|role| role.name.to_string())
+        }
+        Mention::Channel(id) => {
+            let channel_id = ChannelId::new(id);
+
+            // Links usually point to the same server, but may point anywhere
+            cache.guilds().into_iter().find_map(|id| {
+                let guild = cache.guild(id)?;
+
+                match guild.channels.get(&channel_id) {
+                    Some(channel) => Some(channel.name.to_owned()),
+                    None => guild
+                        .threads
+                        .iter()
+                        .find(|thread| thread.id == channel_id)
+                        .map(|thread| thread.name.to_owned()),
+                }
+            })
+        }
+    })
+}
+
+/// Turns custom emoji markup (`<:name:id>`, `<a:name:id>`) into `:name:`, leaving unicode emoji as is.
+pub fn emoji_label(emoji: &str) -> String {
+    let inner = emoji
+        .strip_prefix('<')
+        .and_then(|rest| rest.strip_suffix('>'));
+
+    match inner.and_then(|inner| inner.split(':').nth(1)) {
+        Some(name) => format!(":{}:", name),
+        None => emoji.to_string(),
+    }
+}
+
+/// Delay before the given reconnect attempt, doubling from one second up to five minutes.
+pub fn reconnect_delay(attempt: u32) -> Duration {
+    let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+
+    RECONNECT_BASE_DELAY
+        .saturating_mul(factor)
+        .min(RECONNECT_MAX_DELAY)
+}
+
+/// Returns why reconnecting is pointless, or `None` if the error may be temporary.
+fn fatal_error_reason(e: &serenity::Error) -> Option<String> {
+    match e {
+        serenity::Error::Gateway(GatewayError::InvalidAuthentication) => {
+            Some("Invalid token".to_string())
+        }
+        serenity::Error::Gateway(
+            GatewayError::InvalidGatewayIntents | GatewayError::DisallowedGatewayIntents,
+        ) => Some(
+            "The bot is not allowed to use the required intents, enable them in the Developer Portal"
+                .to_string(),
+        ),
+        serenity::Error::Http(HttpError::UnsuccessfulRequest(res))
+            if res.status_code.as_u16() == 401 =>
+        {
+            Some("Invalid token".to_string())
+        }
+        _ => None,
+    }
+}
+
+/// Returns the channel of a `.../channels/<id>/messages` route, the one messages are sent with.
+fn message_route_channel(path: &str) -> Option<u64> {
+    let (_, rest) = path.split_once("/channels/")?;
+
+    rest.strip_suffix("/messages")?.parse().ok()
+}
+
+fn parse_reaction(emoji: &str) -> Result<ReactionType, ChatError> {
+    ReactionType::try_from(emoji)
+        .map_err(|_| ChatError::InvalidInput(format!("Invalid emoji '{}'", emoji)))
+}
+
+/// Connection state shared by the client task and the event handler, published to the GUI on change.
+#[derive(Clone)]
+struct ConnectionTracker {
+    state: Arc<std::sync::Mutex<ConnectionState>>,
     tx: Sender<DiscordCommEvent>,
+}
+
+impl ConnectionTracker {
+    fn get(&self) -> ConnectionState {
+        self.state.lock().unwrap().clone()
+    }
+
+    async fn set(&self, state: ConnectionState) {
+        *self.state.lock().unwrap() = state.clone();
+
+        DiscordManager::tx_send(&self.tx, DiscordCommEvent::ConnectionStateChanged(state)).await;
+    }
+}
+
+pub struct DiscordManager {
     http_mutex: Arc<Mutex<Option<Arc<Http>>>>,
     cache_mutex: Arc<Mutex<Option<Arc<Cache>>>>,
     client_thread: Option<JoinHandle<()>>,
-    shard_manager: Option<Arc<ShardManager>>,
+    shard_manager_mutex: Arc<Mutex<Option<Arc<ShardManager>>>>,
+    connection: ConnectionTracker,
+    /// Shared with [`DiscordSender`]s
+    webhooks: Arc<Mutex<HashMap<u64, Webhook>>>,
+    /// Shared with the event handler, which learns about rate limits, and [`DiscordSender`]s
+    pacer: Arc<std::sync::Mutex<SendPacer>>,
 }
 
 impl DiscordManager {
     pub fn new(tx: Sender<DiscordCommEvent>) -> Self {
         Self {
-            tx: tx,
             http_mutex: Arc::new(Mutex::new(None)),
             cache_mutex: Arc::new(Mutex::new(None)),
             client_thread: None,
-            shard_manager: None,
+            shard_manager_mutex: Arc::new(Mutex::new(None)),
+            connection: ConnectionTracker {
+                state: Arc::new(std::sync::Mutex::new(ConnectionState::LoggedOut)),
+                tx,
+            },
+            webhooks: Arc::new(Mutex::new(HashMap::new())),
+            pacer: Arc::new(std::sync::Mutex::new(SendPacer::default())),
         }
     }
 
-    async fn send_to_gui(&self, event: DiscordCommEvent) {
-        Self::tx_send(&self.tx, event).await;
-    }
-
     async fn tx_send(tx: &Sender<DiscordCommEvent>, event: DiscordCommEvent) {
         tx.send(event).await.unwrap_or_else(|err| {
             eprintln!("Failed to send DiscordManager -> App: {:?}", err);
         });
     }
 
+    /// Runs the client until logout, reconnecting with increasing delays when the connection fails.
     async fn start_client(&mut self, token: String) {
         self.abort().await;
 
-        let mut new_client = Self::new_client(token, self.tx.clone()).await;
-        let tx = self.tx.clone();
-
         let http_mutex = self.http_mutex.clone();
-        let http_mutex2 = self.http_mutex.clone();
+        let cache_mutex = self.cache_mutex.clone();
+        let shard_manager_mutex = self.shard_manager_mutex.clone();
+        let connection = self.connection.clone();
+        let pacer = self.pacer.clone();
 
-        let mut http = http_mutex.lock().await;
-        let mut cache = self.cache_mutex.lock().await;
+        let thread: JoinHandle<()> = tokio::spawn(async move {
+            let mut attempt: u32 = 0;
 
-        *http = Some(new_client.http.clone());
-        *cache = Some(new_client.cache.clone());
+            connection.set(ConnectionState::Connecting).await;
 
-        self.shard_manager = Some(new_client.shard_manager.clone());
+            loop {
+                let mut client =
+                    match Self::new_client(&token, connection.clone(), pacer.clone()).await {
+                        Ok(client) => client,
+                        Err(e) => {
+                            let reason = format!("Unable to create client: {}", e);
+                            connection.set(ConnectionState::Failed(reason)).await;
+                            return;
+                        }
+                    };
 
-        let thread: JoinHandle<()> = tokio::spawn(async move {
-            let client_res = new_client.start().await;
+                *http_mutex.lock().await = Some(client.http.clone());
+                *cache_mutex.lock().await = Some(client.cache.clone());
+                *shard_manager_mutex.lock().await = Some(client.shard_manager.clone());
 
-            let mut http_mutex = http_mutex2.lock().await;
-            *http_mutex = None;
+                let client_res = client.start().await;
 
-            if let Err(e) = client_res {
-                let mut error_string = format!("{:?}: {}", e, e);
+                *http_mutex.lock().await = None;
 
-                if let serenity::Error::Gateway(e) = e {
-                    if matches!(e, GatewayError::InvalidAuthentication) {
-                        error_string = "Invalid token".to_string();
+                let e = match client_res {
+                    Ok(()) => {
+                        connection.set(ConnectionState::LoggedOut).await;
+                        return;
                     }
+                    Err(e) => e,
+                };
+
+                if let Some(reason) = fatal_error_reason(&e) {
+                    connection.set(ConnectionState::Failed(reason)).await;
+                    return;
                 }
 
-                let event = DiscordCommEvent::Error(error_string);
-                Self::tx_send(&tx, event).await;
+                // Start counting again if the previous connection worked
+                if connection.get() == ConnectionState::Ready {
+                    attempt = 0;
+                }
+
+                attempt += 1;
+                let delay = reconnect_delay(attempt);
+
+                connection
+                    .set(ConnectionState::Reconnecting {
+                        attempt,
+                        delay,
+                        reason: e.to_string(),
+                    })
+                    .await;
+
+                tokio::time::sleep(delay).await;
             }
         });
 
@@ -117,130 +846,635 @@ impl DiscordManager {
             client_thread.abort();
         }
 
-        if let Some(shard_manager) = &self.shard_manager {
+        if let Some(shard_manager) = self.shard_manager_mutex.lock().await.take() {
             shard_manager.shutdown_all().await;
         }
     }
 
-    async fn check_get_http(&mut self) -> Option<Arc<Http>> {
+    async fn get_http(&self) -> Result<Arc<Http>, ChatError> {
         let http = self.http_mutex.lock().await;
 
-        match (*http).clone() {
-            Some(http) => Some(http),
-            None => {
-                self.send_to_gui(DiscordCommEvent::Error("Not logged in".to_string()))
-                    .await;
+        (*http).clone().ok_or_else(ChatError::not_logged_in)
+    }
+
+    async fn get_cache(&self) -> Result<Arc<Cache>, ChatError> {
+        let cache = self.cache_mutex.lock().await;
 
-                None
+        (*cache).clone().ok_or_else(ChatError::not_logged_in)
+    }
+
+    async fn get_bot_member(
+        &self,
+        http: &Arc<Http>,
+        guild_id: GuildId,
+    ) -> Result<Member, ChatError> {
+        let cache = self.get_cache().await?;
+        let bot_id = cache.current_user().id;
+
+        guild_id
+            .member(http, bot_id)
+            .await
+            .map_err(|e| ChatError::from_serenity(e, "get the bot's server profile", None))
+    }
+
+    fn get_channel_permissions(
+        guild: &PartialGuild,
+        member: &Member,
+        channel: &GuildChannel,
+        parent: Option<&GuildChannel>,
+    ) -> Option<ChannelPermissions> {
+        let thread = matches!(
+            ChannelKind::from_channel_type(channel.kind),
+            Some(ChannelKind::Thread)
+        );
+
+        // Threads don't have their own permission overwrites
+        let permissions = match parent {
+            Some(parent) if thread => guild.user_permissions_in(parent, member),
+            _ => guild.user_permissions_in(channel, member),
+        };
+
+        ChannelPermissions::from_permissions(permissions, thread)
+    }
+
+    /// Looks up cached users by username, display name or server nickname.
+    fn find_cached_user(cache: &Cache, query: &str) -> Option<UserSummary> {
+        let matches = |name: Option<&String>| name.is_some_and(|n| n.eq_ignore_ascii_case(query));
+
+        for entry in cache.users().iter() {
+            let user = entry.value();
+
+            if matches(Some(&user.name)) || matches(user.global_name.as_ref()) {
+                return Some(UserSummary {
+                    id: user.id.get(),
+                    name: user.display_name().to_string(),
+                });
+            }
+        }
+
+        for guild_id in cache.guilds() {
+            let Some(guild) = cache.guild(guild_id) else {
+                continue;
+            };
+
+            for member in guild.members.values() {
+                if matches(member.nick.as_ref()) {
+                    return Some(UserSummary {
+                        id: member.user.id.get(),
+                        name: member.display_name().to_string(),
+                    });
+                }
             }
         }
+
+        None
     }
 
-    async fn event_get_available_text_channels(&mut self, guild_id: GuildId) -> Result<(), String> {
-        if let Some(http) = self.check_get_http().await {
-            let guild = http
-                .get_guild(guild_id)
-                .await
-                .map_err(|e| format!("Unable to get server: {}", e))?;
+    async fn new_client(
+        token: &str,
+        connection: ConnectionTracker,
+        pacer: Arc<std::sync::Mutex<SendPacer>>,
+    ) -> serenity::Result<Client> {
+        let intents = GatewayIntents::GUILD_MESSAGES
+            | GatewayIntents::GUILDS
+            | GatewayIntents::GUILD_MEMBERS
+            | GatewayIntents::GUILD_MESSAGE_REACTIONS
+            | GatewayIntents::DIRECT_MESSAGES
+            | GatewayIntents::DIRECT_MESSAGE_REACTIONS
+            | GatewayIntents::GUILD_MESSAGE_TYPING
+            | GatewayIntents::DIRECT_MESSAGE_TYPING
+            | GatewayIntents::MESSAGE_CONTENT;
+
+        Client::builder(token, intents)
+            .event_handler(DiscordHandler {
+                tx: connection.tx.clone(),
+                connection,
+                pacer,
+            })
+            .await
+    }
+}
+
+/// Sends messages on the connection of a [`DiscordManager`], independently of its other requests.
+#[derive(Clone)]
+pub struct DiscordSender {
+    http_mutex: Arc<Mutex<Option<Arc<Http>>>>,
+    cache_mutex: Arc<Mutex<Option<Arc<Cache>>>>,
+    connection: ConnectionTracker,
+    /// Webhooks used for sending, by channel ID
+    webhooks: Arc<Mutex<HashMap<u64, Webhook>>>,
+    pacer: Arc<std::sync::Mutex<SendPacer>>,
+}
+
+impl DiscordSender {
+    async fn get_http(&self) -> Result<Arc<Http>, ChatError> {
+        let http = self.http_mutex.lock().await;
+
+        (*http).clone().ok_or_else(ChatError::not_logged_in)
+    }
+
+    async fn get_cache(&self) -> Result<Arc<Cache>, ChatError> {
+        let cache = self.cache_mutex.lock().await;
+
+        (*cache).clone().ok_or_else(ChatError::not_logged_in)
+    }
+
+    /// Reuses the webhook Dove created in the channel earlier or creates one.
+    async fn get_webhook(
+        &self,
+        http: &Arc<Http>,
+        channel_id: ChannelId,
+    ) -> Result<Webhook, ChatError> {
+        if let Some(webhook) = self.webhooks.lock().await.get(&channel_id.get()) {
+            return Ok(webhook.clone());
+        }
 
-            let channel_map = guild
-                .channels(&http)
+        let bot_id = self.get_cache().await?.current_user().id;
+
+        let existing = channel_id
+            .webhooks(http)
+            .await
+            .map_err(|e| ChatError::from_serenity(e, "get webhooks", Some("Manage Webhooks")))?
+            .into_iter()
+            .find(|webhook| {
+                webhook.token.is_some() && webhook.user.as_ref().is_some_and(|u| u.id == bot_id)
+            });
+
+        let webhook = match existing {
+            Some(webhook) => webhook,
+            None => channel_id
+                .create_webhook(http, CreateWebhook::new(WEBHOOK_NAME))
                 .await
-                .map_err(|e| format!("Unable to get channels: {}", e))?;
+                .map_err(|e| {
+                    ChatError::from_serenity(e, "create webhook", Some("Manage Webhooks"))
+                })?,
+        };
+
+        self.webhooks
+            .lock()
+            .await
+            .insert(channel_id.get(), webhook.clone());
+
+        Ok(webhook)
+    }
+
+    /// Webhooks can't reply, `reply_to` is ignored.
+    async fn send_webhook_message(
+        &self,
+        http: &Arc<Http>,
+        msg: OutgoingMessage,
+    ) -> Result<u64, ChatError> {
+        let Some(identity) = msg.webhook else {
+            return Err(ChatError::Internal("No webhook identity".to_string()));
+        };
+
+        let channel = http
+            .get_channel(ChannelId::new(msg.channel_id))
+            .await
+            .map_err(|e| ChatError::from_serenity(e, "get channel", None))?
+            .guild()
+            .ok_or(ChatError::NotFound(
+                "Webhooks can only be used in server channels".to_string(),
+            ))?;
+
+        let mut builder = ExecuteWebhook::new()
+            .content(msg.content)
+            .username(identity.name);
+
+        if let Some(avatar_url) = identity.avatar_url {
+            builder = builder.avatar_url(avatar_url);
+        }
 
-            let mut res: Vec<GuildChannel> = Vec::new();
+        // Threads use the webhook of their parent channel
+        let webhook_channel_id = match channel.parent_id {
+            Some(parent_id)
+                if ChannelKind::from_channel_type(channel.kind) == Some(ChannelKind::Thread) =>
+            {
+                builder = builder.in_thread(channel.id);
+                parent_id
+            }
+            _ => channel.id,
+        };
+
+        for path in &msg.attachments {
+            let attachment = CreateAttachment::path(path).await.map_err(|e| {
+                ChatError::InvalidInput(format!("Unable to read '{}': {}", path.display(), e))
+            })?;
+
+            builder = builder.add_file(attachment);
+        }
 
-            // TODO: Check if the bot has access to the channels
+        let webhook = self.get_webhook(http, webhook_channel_id).await?;
 
-            for (_id, channel) in channel_map {
-                if !matches!(channel.kind, ChannelType::Text) {
-                    continue;
+        let res = webhook
+            .execute(http, true, builder)
+            .await
+            .map_err(|e| ChatError::from_serenity(e, "send message", None));
+
+        match res {
+            Ok(Some(sent_msg)) => Ok(sent_msg.id.get()),
+            Ok(None) => Err(ChatError::Internal(
+                "Discord didn't return the sent message".to_string(),
+            )),
+            Err(e) => {
+                // The webhook may have been deleted, get a new one next time
+                if matches!(e, ChatError::NotFound(_)) {
+                    self.webhooks.lock().await.remove(&webhook_channel_id.get());
                 }
 
-                res.push(channel);
+                Err(e)
             }
+        }
+    }
 
-            self.send_to_gui(DiscordCommEvent::AvailableTextChannelsListed(res))
-                .await;
+    /// Looks up the slowmode of a channel the first time a message is sent to it.
+    async fn get_slowmode(&self, http: &Arc<Http>, channel_id: ChannelId) -> Duration {
+        if let Some(slowmode) = self.pacer.lock().unwrap().slowmode(channel_id.get()) {
+            return slowmode;
+        }
+
+        let slowmode = self
+            .fetch_slowmode(http, channel_id)
+            .await
+            .unwrap_or_default();
+
+        self.pacer
+            .lock()
+            .unwrap()
+            .set_slowmode(channel_id.get(), slowmode);
+
+        slowmode
+    }
+
+    /// Returns None without slowmode, or if the bot can manage messages or the channel,
+    /// which makes Discord ignore it.
+    async fn fetch_slowmode(&self, http: &Arc<Http>, channel_id: ChannelId) -> Option<Duration> {
+        let cache = self.get_cache().await.ok()?;
+        let channel = http.get_channel(channel_id).await.ok()?.guild()?;
+        let seconds = channel.rate_limit_per_user.filter(|seconds| *seconds > 0)?;
+
+        let bot_id = cache.current_user().id;
+        let exempt = cache.guild(channel.guild_id).is_some_and(|guild| {
+            guild.members.get(&bot_id).is_some_and(|member| {
+                let permissions = guild.user_permissions_in(&channel, member);
+                permissions.manage_messages() || permissions.manage_channels()
+            })
+        });
+
+        match exempt {
+            true => None,
+            false => Some(Duration::from_secs(seconds as u64)),
+        }
+    }
+}
+
+#[async_trait]
+impl MessageSender for DiscordSender {
+    async fn is_ready(&mut self) -> bool {
+        self.connection.get() == ConnectionState::Ready
+    }
+
+    async fn send_message(&mut self, msg: OutgoingMessage) -> Result<u64, ChatError> {
+        let http = self.get_http().await?;
+
+        if msg.webhook.is_some() {
+            return self.send_webhook_message(&http, msg).await;
         }
 
+        let channel_id = ChannelId::new(msg.channel_id);
+        let mut builder = CreateMessage::new().content(msg.content);
+
+        if let Some(reply_to) = msg.reply_to {
+            builder = builder.reference_message((channel_id, MessageId::new(reply_to)));
+        }
+
+        for path in &msg.attachments {
+            let attachment = CreateAttachment::path(path).await.map_err(|e| {
+                ChatError::InvalidInput(format!("Unable to read '{}': {}", path.display(), e))
+            })?;
+
+            builder = builder.add_file(attachment);
+        }
+
+        let permission = if msg.attachments.is_empty() {
+            "Send Messages"
+        } else {
+            "Send Messages or Attach Files"
+        };
+
+        // Known before the first message, so the next one can wait for it. Webhooks aren't slowed.
+        self.get_slowmode(&http, channel_id).await;
+
+        // Serenity would hold the request until Discord allows it
+        if let Some(wait) = self.send_delay(channel_id.get()).await {
+            return Err(ChatError::RateLimited(Some(wait)));
+        }
+
+        let sent_msg = channel_id
+            .send_message(&http, builder)
+            .await
+            .map_err(|e| ChatError::from_serenity(e, "send message", Some(permission)))?;
+
+        self.pacer
+            .lock()
+            .unwrap()
+            .sent(channel_id.get(), Instant::now());
+
+        Ok(sent_msg.id.get())
+    }
+
+    async fn send_delay(&mut self, channel_id: u64) -> Option<Duration> {
+        self.pacer
+            .lock()
+            .unwrap()
+            .wait_time(channel_id, Instant::now())
+    }
+}
+
+#[async_trait]
+impl ChatBackend for DiscordManager {
+    type Sender = DiscordSender;
+
+    async fn login(&mut self, token: String) -> Result<(), ChatError> {
+        self.start_client(token).await;
         Ok(())
     }
 
-    async fn process_event(&mut self, event: DiscordCommEvent) -> Result<(), String> {
-        match event {
-            DiscordCommEvent::Logout => {
-                self.abort().await;
-                Ok(())
-            }
-            DiscordCommEvent::Login(token) => {
-                self.start_client(token).await;
-                Ok(())
-            }
-            DiscordCommEvent::MessageSend(id, content) => {
-                if let Some(http) = self.check_get_http().await {
-                    let _sent_msg = ChannelId::new(id)
-                        .say(http, content)
-                        .await
-                        .map_err(|e| format!("Unable to send message: {}", e))?;
-                }
+    async fn logout(&mut self) {
+        self.abort().await;
+        self.webhooks.lock().await.clear();
+        self.pacer.lock().unwrap().clear();
+        self.connection.set(ConnectionState::LoggedOut).await;
+    }
 
-                Ok(())
-            }
-            DiscordCommEvent::GetGuilds => {
-                if let Some(http) = self.check_get_http().await {
-                    let guilds = http
-                        .get_guilds(None, None)
-                        .await
-                        .map_err(|e| format!("Unable to get servers: {}", e))?;
-
-                    self.send_to_gui(DiscordCommEvent::GuildsListed(guilds))
-                        .await;
-                }
+    fn message_sender(&self) -> DiscordSender {
+        DiscordSender {
+            http_mutex: self.http_mutex.clone(),
+            cache_mutex: self.cache_mutex.clone(),
+            connection: self.connection.clone(),
+            webhooks: self.webhooks.clone(),
+            pacer: self.pacer.clone(),
+        }
+    }
 
-                Ok(())
-            }
-            DiscordCommEvent::GetAvailableTextChannels(guild_id) => {
-                let guild_id = GuildId::new(guild_id);
+    async fn list_guilds(&mut self) -> Result<Vec<GuildSummary>, ChatError> {
+        let http = self.get_http().await?;
 
-                self.event_get_available_text_channels(guild_id).await
-            }
-            _ => Ok(()),
+        let guilds = http
+            .get_guilds(None, None)
+            .await
+            .map_err(|e| ChatError::from_serenity(e, "get servers", None))?;
+
+        Ok(guilds
+            .into_iter()
+            .map(|guild| GuildSummary {
+                id: guild.id.get(),
+                name: guild.name,
+            })
+            .collect())
+    }
+
+    async fn list_channels(&mut self, guild_id: u64) -> Result<Vec<ChannelInfo>, ChatError> {
+        let http = self.get_http().await?;
+
+        let guild = http
+            .get_guild(GuildId::new(guild_id))
+            .await
+            .map_err(|e| ChatError::from_serenity(e, "get server", None))?;
+
+        let channel_map = guild
+            .channels(&http)
+            .await
+            .map_err(|e| ChatError::from_serenity(e, "get channels", None))?;
+
+        let threads = guild
+            .id
+            .get_active_threads(&http)
+            .await
+            .map_err(|e| ChatError::from_serenity(e, "get threads", None))?
+            .threads;
+
+        let mut channels: Vec<GuildChannel> = channel_map.values().cloned().collect();
+        channels.sort_by(|a, b| a.position.cmp(&b.position).then(a.name.cmp(&b.name)));
+
+        let member = self.get_bot_member(&http, guild.id).await?;
+
+        let res = channels
+            .iter()
+            .chain(threads.iter())
+            .filter_map(|channel| {
+                let parent = channel.parent_id.and_then(|id| channel_map.get(&id));
+                let permissions = Self::get_channel_permissions(&guild, &member, channel, parent)?;
+
+                ChannelInfo::from_guild_channel(channel, &guild.name, permissions)
+            })
+            .collect();
+
+        Ok(res)
+    }
+
+    async fn get_channel(&mut self, channel_id: u64) -> Result<ChannelInfo, ChatError> {
+        let http = self.get_http().await?;
+
+        let channel = http
+            .get_channel(ChannelId::new(channel_id))
+            .await
+            .map_err(|e| ChatError::from_serenity(e, "get channel", None))?
+            .guild()
+            .ok_or(ChatError::NotFound(
+                "Only server channels can be joined".to_string(),
+            ))?;
+
+        let guild = http
+            .get_guild(channel.guild_id)
+            .await
+            .map_err(|e| ChatError::from_serenity(e, "get server", None))?;
+
+        let parent = match channel.parent_id {
+            Some(parent_id) => http
+                .get_channel(parent_id)
+                .await
+                .ok()
+                .and_then(|parent| parent.guild()),
+            None => None,
+        };
+
+        let member = self.get_bot_member(&http, guild.id).await?;
+        let permissions = Self::get_channel_permissions(&guild, &member, &channel, parent.as_ref())
+            .ok_or(ChatError::Permission(
+                "The bot can't view this channel".to_string(),
+            ))?;
+
+        ChannelInfo::from_guild_channel(&channel, guild.name, permissions).ok_or(
+            ChatError::NotFound("This channel can't hold messages".to_string()),
+        )
+    }
+
+    async fn get_history(
+        &mut self,
+        channel_id: u64,
+        before: Option<u64>,
+        limit: u8,
+    ) -> Result<Vec<ChatMessage>, ChatError> {
+        let http = self.get_http().await?;
+        let cache = self.get_cache().await?;
+        let mut builder = GetMessages::new().limit(limit);
+
+        if let Some(before) = before {
+            builder = builder.before(MessageId::new(before));
         }
+
+        let messages = ChannelId::new(channel_id)
+            .messages(http, builder)
+            .await
+            .map_err(|e| {
+                ChatError::from_serenity(e, "get messages", Some("Read Message History"))
+            })?;
+
+        // Discord returns the newest messages first
+        Ok(messages
+            .iter()
+            .rev()
+            .map(|msg| ChatMessage::from_cached(msg, &cache))
+            .collect())
     }
 
-    pub async fn start(&mut self, mut rx: Receiver<DiscordCommEvent>) {
-        // Important: http_mutex must not be locked and kept here, or other functions that use it will freeze
+    async fn add_reaction(
+        &mut self,
+        channel_id: u64,
+        message_id: u64,
+        emoji: String,
+    ) -> Result<(), ChatError> {
+        let http = self.get_http().await?;
+        let reaction = parse_reaction(&emoji)?;
 
-        loop {
-            if let Some(event) = rx.recv().await {
-                let res = self.process_event(event).await;
+        http.create_reaction(
+            ChannelId::new(channel_id),
+            MessageId::new(message_id),
+            &reaction,
+        )
+        .await
+        .map_err(|e| ChatError::from_serenity(e, "add reaction", Some("Add Reactions")))
+    }
 
-                if let Err(e) = res {
-                    self.send_to_gui(DiscordCommEvent::Error(e)).await;
-                }
-            }
+    async fn remove_reaction(
+        &mut self,
+        channel_id: u64,
+        message_id: u64,
+        emoji: String,
+    ) -> Result<(), ChatError> {
+        let http = self.get_http().await?;
+        let reaction = parse_reaction(&emoji)?;
+
+        http.delete_reaction_me(
+            ChannelId::new(channel_id),
+            MessageId::new(message_id),
+            &reaction,
+        )
+        .await
+        .map_err(|e| ChatError::from_serenity(e, "remove reaction", None))
+    }
+
+    async fn download_attachment(
+        &mut self,
+        attachment: &AttachmentInfo,
+    ) -> Result<Vec<u8>, ChatError> {
+        let http = self.get_http().await?;
+
+        let downloaded = CreateAttachment::url(http, &attachment.url)
+            .await
+            .map_err(|e| {
+                let action = format!("download '{}'", attachment.filename);
+                ChatError::from_serenity(e, &action, None)
+            })?;
+
+        Ok(downloaded.data)
+    }
+
+    async fn find_user(&mut self, query: String) -> Result<UserSummary, ChatError> {
+        let http = self.get_http().await?;
+
+        if let Ok(id) = query.parse::<NonZeroU64>().map(NonZeroU64::get) {
+            let user = http
+                .get_user(UserId::new(id))
+                .await
+                .map_err(|e| ChatError::from_serenity(e, "get user", None))?;
+
+            return Ok(UserSummary {
+                id,
+                name: user.display_name().to_string(),
+            });
         }
+
+        let cache = self.get_cache().await?;
+
+        Self::find_cached_user(&cache, &query)
+            .ok_or(ChatError::NotFound(format!("User '{}' not found", query)))
     }
 
-    async fn new_client(token: String, tx: Sender<DiscordCommEvent>) -> Client {
-        let intents = GatewayIntents::GUILD_MESSAGES
-            | GatewayIntents::GUILDS
-            | GatewayIntents::GUILD_MEMBERS
-            | GatewayIntents::DIRECT_MESSAGES
-            | GatewayIntents::MESSAGE_CONTENT;
+    async fn open_direct_channel(&mut self, user_id: u64) -> Result<u64, ChatError> {
+        let http = self.get_http().await?;
+
+        let channel = UserId::new(user_id)
+            .create_dm_channel(http)
+            .await
+            .map_err(|e| ChatError::from_serenity(e, "open direct messages", None))?;
+
+        Ok(channel.id.get())
+    }
+
+    async fn list_mention_candidates(
+        &mut self,
+        channel_id: u64,
+    ) -> Result<Vec<MentionCandidate>, ChatError> {
+        let http = self.get_http().await?;
+        let cache = self.get_cache().await?;
 
-        let client = Client::builder(token, intents)
-            .event_handler(DiscordHandler { tx: tx })
+        let channel = http
+            .get_channel(ChannelId::new(channel_id))
             .await
-            .expect("Client error");
+            .map_err(|e| ChatError::from_serenity(e, "get channel", None))?;
 
-        client
+        // Direct messages have nobody else to mention
+        let Some(channel) = channel.guild() else {
+            return Ok(Vec::new());
+        };
+
+        let Some(guild) = cache.guild(channel.guild_id) else {
+            return Ok(Vec::new());
+        };
+
+        let members = guild.members.values().map(|member| MentionCandidate {
+            mention: Mention::User(member.user.id.get()),
+            name: member.display_name().to_string(),
+        });
+
+        let channels = guild
+            .channels
+            .values()
+            .chain(guild.threads.iter())
+            .filter(|channel| ChannelKind::from_channel_type(channel.kind).is_some())
+            .map(|channel| MentionCandidate {
+                mention: Mention::Channel(channel.id.get()),
+                name: channel.name.to_owned(),
+            });
+
+        Ok(members.chain(channels).collect())
+    }
+
+    async fn broadcast_typing(&mut self, channel_id: u64) -> Result<(), ChatError> {
+        let http = self.get_http().await?;
+
+        http.broadcast_typing(ChannelId::new(channel_id))
+            .await
+            .map_err(|e| ChatError::from_serenity(e, "send typing indicator", None))
     }
 }
 
 pub struct DiscordHandler {
     tx: Sender<DiscordCommEvent>,
+    connection: ConnectionTracker,
+    pacer: Arc<std::sync::Mutex<SendPacer>>,
 }
 
 impl DiscordHandler {
@@ -251,14 +1485,127 @@ impl DiscordHandler {
             eprintln!("Failed to send DiscordHandler -> App: {:?}", err);
         });
     }
+
+    fn reaction_update(ctx: &Context, reaction: &Reaction) -> ReactionUpdate {
+        let me = reaction.user_id == Some(ctx.cache.current_user().id);
+
+        ReactionUpdate {
+            channel_id: reaction.channel_id.get(),
+            message_id: reaction.message_id.get(),
+            emoji: reaction.emoji.to_string(),
+            me,
+        }
+    }
 }
 
 #[async_trait]
 impl EventHandler for DiscordHandler {
-    async fn message(&self, _ctx: Context, msg: Message) {
+    async fn message(&self, ctx: Context, msg: Message) {
         println!("Received {}", &msg.content);
 
-        self.send_to_gui(DiscordCommEvent::MessageReceived(msg))
+        let chat_msg = ChatMessage::from_cached(&msg, &ctx.cache);
+
+        self.send_to_gui(DiscordCommEvent::MessageReceived(chat_msg))
+            .await;
+    }
+
+    async fn message_update(
+        &self,
+        ctx: Context,
+        _old_if_available: Option<Message>,
+        _new: Option<Message>,
+        event: MessageUpdateEvent,
+    ) {
+        let channel_id = event.channel_id.get();
+        let id = event.id.get();
+
+        // Updates without content are e.g. embeds being resolved for links
+        if let Some(content) = &event.content {
+            let mentions = event.mentions.as_deref().unwrap_or_default();
+            let content = resolve_markup(content, &ctx.cache, event.guild_id, mentions);
+
+            self.send_to_gui(DiscordCommEvent::MessageEdited(channel_id, id, content))
+                .await;
+        }
+
+        if let Some(embeds) = event.embeds {
+            let embeds = embeds.iter().map(EmbedInfo::from).collect();
+
+            self.send_to_gui(DiscordCommEvent::EmbedsUpdated(channel_id, id, embeds))
+                .await;
+        }
+    }
+
+    async fn message_delete(
+        &self,
+        _ctx: Context,
+        channel_id: ChannelId,
+        deleted_message_id: MessageId,
+        _guild_id: Option<GuildId>,
+    ) {
+        let event =
+            DiscordCommEvent::MessagesDeleted(channel_id.get(), vec![deleted_message_id.get()]);
+        self.send_to_gui(event).await;
+    }
+
+    async fn message_delete_bulk(
+        &self,
+        _ctx: Context,
+        channel_id: ChannelId,
+        multiple_deleted_messages_ids: Vec<MessageId>,
+        _guild_id: Option<GuildId>,
+    ) {
+        let ids = multiple_deleted_messages_ids
+            .iter()
+            .map(|id| id.get())
+            .collect();
+
+        self.send_to_gui(DiscordCommEvent::MessagesDeleted(channel_id.get(), ids))
+            .await;
+    }
+
+    async fn reaction_add(&self, ctx: Context, add_reaction: Reaction) {
+        let update = Self::reaction_update(&ctx, &add_reaction);
+        self.send_to_gui(DiscordCommEvent::ReactionAdded(update))
+            .await;
+    }
+
+    async fn reaction_remove(&self, ctx: Context, removed_reaction: Reaction) {
+        let update = Self::reaction_update(&ctx, &removed_reaction);
+        self.send_to_gui(DiscordCommEvent::ReactionRemoved(update))
+            .await;
+    }
+
+    async fn typing_start(&self, ctx: Context, event: TypingStartEvent) {
+        if event.user_id == ctx.cache.current_user().id {
+            return;
+        }
+
+        let cached_name = match &event.member {
+            Some(member) => Some(member.display_name().to_string()),
+            None => ctx
+                .cache
+                .user(event.user_id)
+                .map(|user| user.display_name().to_string()),
+        };
+
+        let name = match cached_name {
+            Some(name) => name,
+            None => match event.user_id.to_user(&ctx).await {
+                Ok(user) => user.display_name().to_string(),
+                Err(_) => event.user_id.to_string(),
+            },
+        };
+
+        let update = TypingUpdate {
+            channel_id: event.channel_id.get(),
+            user: UserSummary {
+                id: event.user_id.get(),
+                name,
+            },
+        };
+
+        self.send_to_gui(DiscordCommEvent::TypingStarted(update))
             .await;
     }
 
@@ -266,8 +1613,145 @@ impl EventHandler for DiscordHandler {
         println!("Discord ready")
     }
 
-    async fn cache_ready(&self, _ctx: Context, _guilds: Vec<GuildId>) {
+    async fn cache_ready(&self, ctx: Context, _guilds: Vec<GuildId>) {
         println!("Discord cache ready");
-        self.send_to_gui(DiscordCommEvent::Ready).await;
+
+        let user = {
+            let current_user = ctx.cache.current_user();
+
+            UserSummary {
+                id: current_user.id.get(),
+                name: current_user.display_name().to_string(),
+            }
+        };
+
+        self.connection.set(ConnectionState::Ready).await;
+        self.send_to_gui(DiscordCommEvent::Ready(user)).await;
+    }
+
+    /// Serenity waits out rate limits by itself, sends are paced so the user knows why.
+    async fn ratelimit(&self, data: RatelimitInfo) {
+        let channel_id = match message_route_channel(&data.path) {
+            _ if data.global => None,
+            Some(channel_id) if data.method == LightMethod::Post => Some(channel_id),
+            // Only sending messages is worth showing
+            _ => return,
+        };
+
+        self.pacer
+            .lock()
+            .unwrap()
+            .limit(channel_id, Instant::now() + data.timeout);
+
+        self.send_to_gui(DiscordCommEvent::SendDelayed(channel_id, data.timeout))
+            .await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serenity::all::GatewayError;
+
+    use crate::discord::{
+        ChatError, Mention, emoji_label, fatal_error_reason, message_route_channel, parse_reaction,
+        reconnect_delay, replace_markup,
+    };
+
+    #[test]
+    fn test_emoji_label() {
+        assert_eq!(emoji_label("👍"), "👍");
+        assert_eq!(emoji_label("<:dove:123456>"), ":dove:");
+        assert_eq!(emoji_label("<a:party:42>"), ":party:");
+    }
+
+    #[test]
+    fn test_replace_markup() {
+        let resolve = |mention| match mention {
+            Mention::User(1) => Some("Wolfyxon".to_string()),
+            Mention::Role(2) => Some("Moderators".to_string()),
+            Mention::Channel(3) => Some("general".to_string()),
+            _ => None,
+        };
+
+        assert_eq!(
+            replace_markup("Hi <@1> and <@!1>, ask <@&2> in <#3>", resolve),
+            "Hi @Wolfyxon and @Wolfyxon, ask @Moderators in #general"
+        );
+        assert_eq!(
+            replace_markup("<@9> <@&9> <#9>", resolve),
+            "@unknown-user @unknown-role #unknown"
+        );
+        assert_eq!(
+            replace_markup("Nice <:dove:123> <a:party:42>", resolve),
+            "Nice :dove: :party:"
+        );
+        assert_eq!(replace_markup("a < b > c <@x>", resolve), "a < b > c <@x>");
+        assert_eq!(
+            replace_markup("<@0> <@&0> <#0>", resolve),
+            "<@0> <@&0> <#0>"
+        );
+    }
+
+    #[test]
+    fn test_reconnect_delay() {
+        assert_eq!(reconnect_delay(1), Duration::from_secs(1));
+        assert_eq!(reconnect_delay(2), Duration::from_secs(2));
+        assert_eq!(reconnect_delay(5), Duration::from_secs(16));
+        assert_eq!(reconnect_delay(20), Duration::from_secs(5 * 60));
+        assert_eq!(reconnect_delay(u32::MAX), Duration::from_secs(5 * 60));
+    }
+
+    #[test]
+    fn test_message_route_channel() {
+        assert_eq!(
+            message_route_channel("https://discord.com/api/v10/channels/123/messages"),
+            Some(123)
+        );
+        assert_eq!(
+            message_route_channel("https://discord.com/api/v10/channels/123/typing"),
+            None
+        );
+        assert_eq!(
+            message_route_channel("https://discord.com/api/v10/channels/123/messages/4/reactions"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_fatal_errors() {
+        let invalid_token = serenity::Error::Gateway(GatewayError::InvalidAuthentication);
+        assert_eq!(
+            fatal_error_reason(&invalid_token),
+            Some("Invalid token".to_string())
+        );
+
+        let disconnected = serenity::Error::Gateway(GatewayError::Closed(None));
+        assert_eq!(fatal_error_reason(&disconnected), None);
+    }
+
+    #[test]
+    fn test_error_kinds() {
+        let disconnected = serenity::Error::Gateway(GatewayError::Closed(None));
+        assert!(matches!(
+            ChatError::from_serenity(disconnected, "get servers", None),
+            ChatError::Network(_)
+        ));
+
+        let other = serenity::Error::Other("oops");
+        assert_eq!(
+            ChatError::from_serenity(other, "get servers", None),
+            ChatError::Internal("Unable to get servers: oops".to_string())
+        );
+
+        assert_eq!(
+            ChatError::RateLimited(Some(Duration::from_millis(2500))).to_string(),
+            "Rate limited, try again in 3s"
+        );
+        assert!(matches!(
+            parse_reaction("<:broken>"),
+            Err(ChatError::InvalidInput(_))
+        ));
     }
 }
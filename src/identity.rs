@@ -0,0 +1,175 @@
+/// Start of the tag naming the Dove user who wrote a message sent as the bot.
+///
+/// The full format is `[dove:<name>] <content>`, or `[dove:<name>|#rrggbb] <content>`
+/// with the name colour in hex. Names can't contain `]`, `|` or line breaks.
+pub const TAG_PREFIX: &str = "[dove:";
+pub const MAX_NICKNAME_LEN: usize = 32;
+
+/// Who the local Dove user is.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Identity {
+    pub nickname: Option<String>,
+    /// RGB, e.g. `0xff8800`
+    pub color: Option<u32>,
+}
+
+/// Sender named by a message tag.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SenderTag {
+    pub name: String,
+    pub color: Option<u32>,
+}
+
+impl Identity {
+    /// Tag to put before outgoing messages, None without a nickname.
+    pub fn tag(&self) -> Option<String> {
+        let name = self.nickname.as_ref()?;
+
+        Some(match self.color {
+            Some(color) => format!("{}{}|{}] ", TAG_PREFIX, name, format_color(color)),
+            None => format!("{}{}] ", TAG_PREFIX, name),
+        })
+    }
+
+    /// Parses `nick <name>` and `color <#rrggbb>` lines.
+    pub fn parse(text: &str) -> Self {
+        let mut res = Self::default();
+
+        for line in text.lines().map(str::trim) {
+            let Some((key, value)) = line.split_once(' ') else {
+                continue;
+            };
+
+            match key {
+                "nick" if validate_nickname(value).is_ok() => {
+                    res.nickname = Some(value.to_string())
+                }
+                "color" => res.color = parse_color(value),
+                _ => (),
+            }
+        }
+
+        res
+    }
+
+    pub fn serialize(&self) -> String {
+        let mut lines: Vec<String> = Vec::new();
+
+        if let Some(nickname) = &self.nickname {
+            lines.push(format!("nick {}", nickname));
+        }
+
+        if let Some(color) = self.color {
+            lines.push(format!("color {}", format_color(color)));
+        }
+
+        lines.join("\n")
+    }
+}
+
+pub fn validate_nickname(name: &str) -> Result<(), String> {
+    let len = name.chars().count();
+
+    if len == 0 || len > MAX_NICKNAME_LEN || name.trim() != name {
+        return Err(format!(
+            "Nicknames must have 1 to {} characters and can't start or end with spaces",
+            MAX_NICKNAME_LEN
+        ));
+    }
+
+    if name.contains([']', '|', '\n', '\r']) {
+        return Err("Nicknames can't contain ], | or line breaks".to_string());
+    }
+
+    Ok(())
+}
+
+/// Parses `#rrggbb`.
+pub fn parse_color(text: &str) -> Option<u32> {
+    let hex = text.trim().strip_prefix('#')?;
+
+    if hex.len() != 6 {
+        return None;
+    }
+
+    u32::from_str_radix(hex, 16).ok()
+}
+
+pub fn format_color(color: u32) -> String {
+    format!("#{:06x}", color & 0xffffff)
+}
+
+/// Splits a tagged message into its sender and the content after the tag.
+pub fn parse_tag(content: &str) -> Option<(SenderTag, &str)> {
+    let rest = content.strip_prefix(TAG_PREFIX)?;
+    let (inner, content) = rest.split_once(']')?;
+
+    let (name, color) = match inner.split_once('|') {
+        Some((name, color)) => (name, Some(parse_color(color)?)),
+        None => (inner, None),
+    };
+
+    validate_nickname(name).ok()?;
+
+    let tag = SenderTag {
+        name: name.to_string(),
+        color,
+    };
+
+    Some((tag, content.strip_prefix(' ').unwrap_or(content)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::identity::{Identity, SenderTag, parse_tag, validate_nickname};
+
+    #[test]
+    fn test_tags() {
+        let mut identity = Identity::default();
+        assert_eq!(identity.tag(), None);
+
+        identity.nickname = Some("Wolfyxon".to_string());
+        assert_eq!(identity.tag().unwrap(), "[dove:Wolfyxon] ");
+
+        identity.color = Some(0xff8800);
+        let tagged = format!("{}Hello [world]", identity.tag().unwrap());
+        assert_eq!(tagged, "[dove:Wolfyxon|#ff8800] Hello [world]");
+
+        assert_eq!(
+            parse_tag(&tagged),
+            Some((
+                SenderTag {
+                    name: "Wolfyxon".to_string(),
+                    color: Some(0xff8800),
+                },
+                "Hello [world]"
+            ))
+        );
+
+        assert_eq!(parse_tag("[dove:] empty name"), None);
+        assert_eq!(parse_tag("[dove:Owl|orange] bad color"), None);
+        assert_eq!(parse_tag("[other:Owl] hi"), None);
+    }
+
+    #[test]
+    fn test_nicknames() {
+        assert!(validate_nickname("Dove user").is_ok());
+        assert!(validate_nickname(" padded").is_err());
+        assert!(validate_nickname("a|b").is_err());
+        assert!(validate_nickname(&"x".repeat(33)).is_err());
+    }
+
+    #[test]
+    fn test_serialization() {
+        let identity = Identity {
+            nickname: Some("Dove user".to_string()),
+            color: Some(0x00ff00),
+        };
+
+        assert_eq!(Identity::parse(&identity.serialize()), identity);
+        assert_eq!(
+            Identity::parse("nick a]b\ncolor green"),
+            Identity::default()
+        );
+    }
+}
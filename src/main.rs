@@ -2,27 +2,37 @@
 
 use eframe::egui;
 use std::process::exit;
-use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio::sync::mpsc::{self, Receiver, Sender, UnboundedReceiver, UnboundedSender};
 
 use crate::{
     app::App,
-    discord::{DiscordCommEvent, DiscordManager},
-    utils::comm::{COMM_BUFFER_SIZE, MPSCChannel},
+    backend::Dispatcher,
+    discord::{DiscordCommEvent, DiscordManager, RequestEnvelope},
+    utils::comm::{COMM_BUFFER_SIZE, MPSCChannel, UnboundedMPSCChannel},
 };
 
+mod accounts;
 mod app;
+mod backend;
 mod commands;
+mod completion;
 mod config;
 mod crypto;
 mod discord;
+mod identity;
+mod outbox;
+mod ratelimit;
+mod subscriptions;
+mod tabs;
 mod utils;
+mod webhooks;
 
 #[tokio::main] // Even though main doesn't need to be async, this macro is required for tokio to work
 async fn main() {
     let (tx_dc_to_gui, rx_dc_to_gui): MPSCChannel<DiscordCommEvent> =
         mpsc::channel(COMM_BUFFER_SIZE);
-    let (tx_gui_to_dc, rx_gui_to_dc): MPSCChannel<DiscordCommEvent> =
-        mpsc::channel(COMM_BUFFER_SIZE);
+    let (tx_gui_to_dc, rx_gui_to_dc): UnboundedMPSCChannel<RequestEnvelope> =
+        mpsc::unbounded_channel();
 
     let _discord_thread = tokio::spawn(async {
         start_discord(tx_dc_to_gui, rx_gui_to_dc).await;
@@ -31,7 +41,10 @@ async fn main() {
     start_gui(tx_gui_to_dc, rx_dc_to_gui); // NOTE: egui must run on main thread
 }
 
-fn start_gui(tx_gui_to_dc: Sender<DiscordCommEvent>, rx_dc_to_gui: Receiver<DiscordCommEvent>) {
+fn start_gui(
+    tx_gui_to_dc: UnboundedSender<RequestEnvelope>,
+    rx_dc_to_gui: Receiver<DiscordCommEvent>,
+) {
     let viewport = egui::ViewportBuilder::default()
         .with_inner_size([400.0, 200.0])
         .with_transparent(true)
@@ -54,8 +67,10 @@ fn start_gui(tx_gui_to_dc: Sender<DiscordCommEvent>, rx_dc_to_gui: Receiver<Disc
 
 async fn start_discord(
     tx_dc_to_gui: Sender<DiscordCommEvent>,
-    rx_gui_to_dc: Receiver<DiscordCommEvent>,
+    rx_gui_to_dc: UnboundedReceiver<RequestEnvelope>,
 ) {
-    let mut mgr = DiscordManager::new(tx_dc_to_gui);
-    mgr.start(rx_gui_to_dc).await;
+    let mgr = DiscordManager::new(tx_dc_to_gui.clone());
+    let mut dispatcher = Dispatcher::new(mgr, tx_dc_to_gui).with_saved_outbox();
+
+    dispatcher.start(rx_gui_to_dc).await;
 }
@@ -0,0 +1,297 @@
+use std::path::PathBuf;
+
+use crate::discord::{OutgoingMessage, WebhookIdentity};
+
+/// First field of lines with webhook fields. Lines saved before webhooks were supported
+/// start with the ID.
+const FORMAT_VERSION: &str = "v2";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OutboxStatus {
+    /// Waiting for a connection
+    Pending,
+    /// Sent as the message with this ID
+    Sent(u64),
+    Failed(String),
+}
+
+/// Outgoing message with a local ID, so the GUI can show it before Discord assigns one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutboxEntry {
+    pub id: u64,
+    pub message: OutgoingMessage,
+    pub status: OutboxStatus,
+}
+
+/// Messages that weren't sent yet, in the order they were written.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Outbox {
+    entries: Vec<OutboxEntry>,
+    /// IDs aren't reused within a session, even after messages leave the outbox
+    last_id: u64,
+}
+
+impl Outbox {
+    pub fn entries(&self) -> &[OutboxEntry] {
+        &self.entries
+    }
+
+    pub fn push(&mut self, message: OutgoingMessage) -> OutboxEntry {
+        self.last_id += 1;
+
+        let entry = OutboxEntry {
+            id: self.last_id,
+            message,
+            status: OutboxStatus::Pending,
+        };
+
+        self.entries.push(entry.clone());
+        entry
+    }
+
+    pub fn get(&self, id: u64) -> Option<&OutboxEntry> {
+        self.entries.iter().find(|e| e.id == id)
+    }
+
+    pub fn pending_ids(&self) -> Vec<u64> {
+        self.entries
+            .iter()
+            .filter(|e| e.status == OutboxStatus::Pending)
+            .map(|e| e.id)
+            .collect()
+    }
+
+    /// Updates the status and returns the updated entry. Sent entries leave the outbox.
+    pub fn set_status(&mut self, id: u64, status: OutboxStatus) -> Option<OutboxEntry> {
+        let idx = self.entries.iter().position(|e| e.id == id)?;
+        self.entries[idx].status = status;
+
+        if matches!(self.entries[idx].status, OutboxStatus::Sent(_)) {
+            Some(self.entries.remove(idx))
+        } else {
+            Some(self.entries[idx].clone())
+        }
+    }
+
+    pub fn remove(&mut self, id: u64) -> Option<OutboxEntry> {
+        let idx = self.entries.iter().position(|e| e.id == id)?;
+        Some(self.entries.remove(idx))
+    }
+
+    /// Parses lines of tab separated fields: [`FORMAT_VERSION`], ID, channel ID, reply ID, status,
+    /// failure reason, webhook name and avatar URL, content and attachment paths.
+    /// Tabs, newlines and backslashes in fields are escaped.
+    pub fn parse(text: &str) -> Self {
+        let entries: Vec<OutboxEntry> = text.lines().filter_map(Self::parse_entry).collect();
+        let last_id = entries.iter().map(|e| e.id).max().unwrap_or(0);
+
+        Self { entries, last_id }
+    }
+
+    fn parse_entry(line: &str) -> Option<OutboxEntry> {
+        let fields: Vec<String> = line.split('\t').map(unescape).collect();
+
+        let (fields, versioned) = match fields.split_first() {
+            Some((version, rest)) if version == FORMAT_VERSION => (rest, true),
+            _ => (fields.as_slice(), false),
+        };
+
+        let [id, channel_id, reply_to, status, reason, rest @ ..] = fields else {
+            return None;
+        };
+
+        let (webhook, content, attachments) = match rest {
+            [webhook_name, avatar_url, content, attachments @ ..] if versioned => {
+                let webhook = (!webhook_name.is_empty()).then(|| WebhookIdentity {
+                    name: webhook_name.to_owned(),
+                    avatar_url: (!avatar_url.is_empty()).then(|| avatar_url.to_owned()),
+                });
+
+                (webhook, content, attachments)
+            }
+            [content, attachments @ ..] if !versioned => (None, content, attachments),
+            _ => return None,
+        };
+
+        let status = match status.as_str() {
+            "pending" => OutboxStatus::Pending,
+            "failed" => OutboxStatus::Failed(reason.to_owned()),
+            _ => return None,
+        };
+
+        Some(OutboxEntry {
+            id: id.parse().ok()?,
+            message: OutgoingMessage {
+                channel_id: channel_id.parse().ok()?,
+                content: content.to_owned(),
+                reply_to: reply_to.parse().ok(),
+                attachments: attachments.iter().map(PathBuf::from).collect(),
+                webhook,
+            },
+            status,
+        })
+    }
+
+    pub fn serialize(&self) -> String {
+        let lines: Vec<String> = self
+            .entries
+            .iter()
+            .filter_map(|entry| {
+                let (status, reason) = match &entry.status {
+                    OutboxStatus::Pending => ("pending", ""),
+                    OutboxStatus::Failed(reason) => ("failed", reason.as_str()),
+                    OutboxStatus::Sent(_) => return None,
+                };
+
+                let msg = &entry.message;
+                let (webhook_name, avatar_url) = match &msg.webhook {
+                    Some(identity) => (
+                        identity.name.as_str(),
+                        identity.avatar_url.as_deref().unwrap_or_default(),
+                    ),
+                    None => ("", ""),
+                };
+
+                let mut fields = vec![
+                    FORMAT_VERSION.to_string(),
+                    entry.id.to_string(),
+                    msg.channel_id.to_string(),
+                    msg.reply_to.map(|id| id.to_string()).unwrap_or_default(),
+                    status.to_string(),
+                    escape(reason),
+                    escape(webhook_name),
+                    escape(avatar_url),
+                    escape(&msg.content),
+                ];
+
+                fields.extend(
+                    msg.attachments
+                        .iter()
+                        .map(|path| escape(&path.to_string_lossy())),
+                );
+
+                Some(fields.join("\t"))
+            })
+            .collect();
+
+        lines.join("\n")
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+}
+
+fn unescape(text: &str) -> String {
+    let mut res = String::with_capacity(text.len());
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            res.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('t') => res.push('\t'),
+            Some('n') => res.push('\n'),
+            Some(other) => res.push(other),
+            None => res.push('\\'),
+        }
+    }
+
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{
+        discord::{OutgoingMessage, WebhookIdentity},
+        outbox::{Outbox, OutboxStatus},
+    };
+
+    fn message(content: &str) -> OutgoingMessage {
+        OutgoingMessage {
+            channel_id: 10,
+            content: content.to_string(),
+            reply_to: None,
+            attachments: Vec::new(),
+            webhook: None,
+        }
+    }
+
+    #[test]
+    fn test_statuses() {
+        let mut outbox = Outbox::default();
+        let first = outbox.push(message("first"));
+        let second = outbox.push(message("second"));
+
+        assert_eq!(outbox.pending_ids(), vec![first.id, second.id]);
+
+        outbox.set_status(
+            first.id,
+            OutboxStatus::Failed("Missing Permissions".to_string()),
+        );
+        assert_eq!(outbox.pending_ids(), vec![second.id]);
+
+        let sent = outbox
+            .set_status(second.id, OutboxStatus::Sent(500))
+            .unwrap();
+        assert_eq!(sent.status, OutboxStatus::Sent(500));
+        assert!(outbox.get(second.id).is_none());
+
+        assert!(outbox.remove(first.id).is_some());
+        assert!(outbox.entries().is_empty());
+        assert_eq!(outbox.push(message("third")).id, 3);
+    }
+
+    #[test]
+    fn test_serialization() {
+        let mut outbox = Outbox::default();
+
+        let mut msg = message("tab\there\nnew line \\ backslash");
+        msg.reply_to = Some(7);
+        msg.attachments.push(PathBuf::from("/tmp/dove.png"));
+
+        outbox.push(msg);
+
+        let mut webhook_msg = message("as someone else");
+        webhook_msg.webhook = Some(WebhookIdentity {
+            name: "Wolfyxon".to_string(),
+            avatar_url: None,
+        });
+        outbox.push(webhook_msg);
+        let failed = outbox.push(message("nope"));
+        outbox.set_status(failed.id, OutboxStatus::Failed("No access".to_string()));
+
+        assert_eq!(Outbox::parse(&outbox.serialize()), outbox);
+        assert_eq!(
+            Outbox::parse("garbage\n1\tnot-a-channel"),
+            Outbox::default()
+        );
+    }
+
+    #[test]
+    fn test_unversioned_lines() {
+        let outbox = Outbox::parse(
+            "1\t10\t\tpending\t\thello\n2\t10\t7\tfailed\tNo access\tpic\t/tmp/a.png",
+        );
+        let entries = outbox.entries();
+
+        assert_eq!(entries[0].message, message("hello"));
+
+        let mut with_file = message("pic");
+        with_file.reply_to = Some(7);
+        with_file.attachments.push(PathBuf::from("/tmp/a.png"));
+
+        assert_eq!(entries[1].message, with_file);
+        assert_eq!(
+            entries[1].status,
+            OutboxStatus::Failed("No access".to_string())
+        );
+    }
+}
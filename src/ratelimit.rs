@@ -0,0 +1,140 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+/// Identical messages allowed within [`FLOOD_WINDOW`] before more are refused.
+pub const FLOOD_REPEATS: usize = 3;
+pub const FLOOD_WINDOW: Duration = Duration::from_secs(5);
+
+/// Tracks when messages can be sent again, from slowmode and the rate limits Discord reported.
+#[derive(Debug, Default)]
+pub struct SendPacer {
+    slowmode: HashMap<u64, Duration>,
+    /// Earliest next send by channel
+    channels: HashMap<u64, Instant>,
+    /// Applies to all channels
+    global: Option<Instant>,
+}
+
+impl SendPacer {
+    pub fn slowmode(&self, channel_id: u64) -> Option<Duration> {
+        self.slowmode.get(&channel_id).copied()
+    }
+
+    pub fn set_slowmode(&mut self, channel_id: u64, slowmode: Duration) {
+        self.slowmode.insert(channel_id, slowmode);
+    }
+
+    /// Blocks sending until `until`, in one channel or all of them.
+    pub fn limit(&mut self, channel_id: Option<u64>, until: Instant) {
+        let current = match channel_id {
+            Some(channel_id) => self.channels.entry(channel_id).or_insert(until),
+            None => self.global.get_or_insert(until),
+        };
+
+        *current = until.max(*current);
+    }
+
+    /// Starts the slowmode of the channel after sending.
+    pub fn sent(&mut self, channel_id: u64, now: Instant) {
+        if let Some(slowmode) = self.slowmode(channel_id)
+            && !slowmode.is_zero()
+        {
+            self.limit(Some(channel_id), now + slowmode);
+        }
+    }
+
+    /// Returns how long to wait before sending to the channel, None if it can be sent to now.
+    pub fn wait_time(&self, channel_id: u64, now: Instant) -> Option<Duration> {
+        let until = [self.channels.get(&channel_id).copied(), self.global]
+            .into_iter()
+            .flatten()
+            .max()?;
+
+        until
+            .checked_duration_since(now)
+            .filter(|wait| !wait.is_zero())
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// Refuses accidental floods, like a message sent many times by holding Enter.
+#[derive(Debug, Default)]
+pub struct FloodGuard {
+    recent: VecDeque<(Instant, String)>,
+}
+
+impl FloodGuard {
+    /// Returns false if the content was already sent [`FLOOD_REPEATS`] times within
+    /// [`FLOOD_WINDOW`]. Allowed messages are remembered.
+    pub fn check(&mut self, content: &str, now: Instant) -> bool {
+        self.recent
+            .retain(|(sent_at, _)| now.duration_since(*sent_at) < FLOOD_WINDOW);
+
+        let repeats = self
+            .recent
+            .iter()
+            .filter(|(_, recent)| recent == content)
+            .count();
+
+        if repeats >= FLOOD_REPEATS {
+            return false;
+        }
+
+        self.recent.push_back((now, content.to_string()));
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::ratelimit::{FLOOD_REPEATS, FLOOD_WINDOW, FloodGuard, SendPacer};
+
+    #[test]
+    fn test_pacing() {
+        let now = Instant::now();
+        let mut pacer = SendPacer::default();
+        pacer.set_slowmode(10, Duration::from_secs(5));
+
+        pacer.sent(20, now);
+        assert_eq!(pacer.wait_time(20, now), None);
+
+        pacer.sent(10, now);
+        assert_eq!(
+            pacer.wait_time(10, now + Duration::from_secs(2)),
+            Some(Duration::from_secs(3))
+        );
+        assert_eq!(pacer.wait_time(10, now + Duration::from_secs(5)), None);
+
+        // A shorter limit doesn't shorten the wait
+        pacer.limit(Some(10), now + Duration::from_secs(1));
+        assert_eq!(pacer.wait_time(10, now), Some(Duration::from_secs(5)));
+
+        pacer.limit(None, now + Duration::from_secs(8));
+        assert_eq!(pacer.wait_time(20, now), Some(Duration::from_secs(8)));
+
+        pacer.clear();
+        assert_eq!(pacer.wait_time(10, now), None);
+        assert_eq!(pacer.slowmode(10), None);
+    }
+
+    #[test]
+    fn test_floods() {
+        let now = Instant::now();
+        let mut guard = FloodGuard::default();
+
+        for _ in 0..FLOOD_REPEATS {
+            assert!(guard.check("spam", now));
+        }
+
+        assert!(!guard.check("spam", now));
+        assert!(guard.check("something else", now));
+        assert!(guard.check("spam", now + FLOOD_WINDOW));
+    }
+}
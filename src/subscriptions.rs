@@ -0,0 +1,96 @@
+use std::{collections::BTreeSet, num::NonZeroU64};
+
+pub const DMS_KEYWORD: &str = "dms";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Subscriptions {
+    pub channels: BTreeSet<u64>,
+    pub direct_messages: bool,
+}
+
+impl Default for Subscriptions {
+    fn default() -> Self {
+        Self {
+            channels: BTreeSet::new(),
+            direct_messages: true,
+        }
+    }
+}
+
+impl Subscriptions {
+    pub fn is_subscribed(
+        &self,
+        channel_id: u64,
+        private: bool,
+        active_channel: Option<u64>,
+    ) -> bool {
+        if private && self.direct_messages {
+            return true;
+        }
+
+        active_channel == Some(channel_id) || self.channels.contains(&channel_id)
+    }
+
+    pub fn parse(text: &str) -> Self {
+        let mut res = Self {
+            channels: BTreeSet::new(),
+            direct_messages: false,
+        };
+
+        for line in text.lines().map(str::trim) {
+            if line == DMS_KEYWORD {
+                res.direct_messages = true;
+            } else if let Ok(id) = line.parse::<NonZeroU64>() {
+                res.channels.insert(id.get());
+            }
+        }
+
+        res
+    }
+
+    pub fn serialize(&self) -> String {
+        let mut lines: Vec<String> = self.channels.iter().map(|id| id.to_string()).collect();
+
+        if self.direct_messages {
+            lines.push(DMS_KEYWORD.to_string());
+        }
+
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::subscriptions::Subscriptions;
+
+    #[test]
+    fn test_is_subscribed() {
+        let mut subs = Subscriptions::default();
+        subs.channels.insert(10);
+
+        assert!(subs.is_subscribed(10, false, None));
+        assert!(subs.is_subscribed(20, false, Some(20)));
+        assert!(!subs.is_subscribed(30, false, Some(20)));
+        assert!(subs.is_subscribed(40, true, None));
+
+        subs.direct_messages = false;
+
+        assert!(!subs.is_subscribed(40, true, None));
+    }
+
+    #[test]
+    fn test_parse_serialize() {
+        let mut subs = Subscriptions::default();
+        subs.channels.insert(123);
+        subs.channels.insert(456);
+
+        assert_eq!(Subscriptions::parse(&subs.serialize()), subs);
+
+        subs.direct_messages = false;
+
+        assert_eq!(Subscriptions::parse(&subs.serialize()), subs);
+
+        // Channel ID 0 doesn't exist, it can only come from an older version
+        assert_eq!(Subscriptions::parse("0\n123\n456"), subs);
+    }
+}
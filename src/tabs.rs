@@ -0,0 +1,198 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TabId {
+    /// Command output and notices from Dove itself
+    System,
+    Channel(u64),
+}
+
+pub struct Tab<M> {
+    pub id: TabId,
+    pub title: String,
+    pub messages: Vec<M>,
+    /// Messages received since the tab was last viewed
+    pub unread: usize,
+    /// Unread messages that mention the user
+    pub mentions: usize,
+}
+
+/// Message buffers shown as tabs. The system tab is always first and can't be closed.
+pub struct Tabs<M> {
+    tabs: Vec<Tab<M>>,
+    selected: usize,
+}
+
+impl<M> Tabs<M> {
+    pub fn new(system_title: &str) -> Self {
+        Self {
+            tabs: vec![Tab {
+                id: TabId::System,
+                title: system_title.to_string(),
+                messages: Vec::new(),
+                unread: 0,
+                mentions: 0,
+            }],
+            selected: 0,
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Tab<M>> {
+        self.tabs.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Tab<M>> {
+        self.tabs.iter_mut()
+    }
+
+    pub fn get(&self, id: TabId) -> Option<&Tab<M>> {
+        self.tabs.iter().find(|tab| tab.id == id)
+    }
+
+    pub fn get_mut(&mut self, id: TabId) -> Option<&mut Tab<M>> {
+        self.tabs.iter_mut().find(|tab| tab.id == id)
+    }
+
+    pub fn system_mut(&mut self) -> &mut Tab<M> {
+        &mut self.tabs[0]
+    }
+
+    /// Returns the tab, adding it at the end if it's not open yet.
+    pub fn open(&mut self, id: TabId, title: &str) -> &mut Tab<M> {
+        let idx = match self.tabs.iter().position(|tab| tab.id == id) {
+            Some(idx) => idx,
+            None => {
+                self.tabs.push(Tab {
+                    id,
+                    title: title.to_string(),
+                    messages: Vec::new(),
+                    unread: 0,
+                    mentions: 0,
+                });
+                self.tabs.len() - 1
+            }
+        };
+
+        &mut self.tabs[idx]
+    }
+
+    /// Counts a new message, unless the tab is being viewed.
+    pub fn mark_unread(&mut self, id: TabId, mention: bool) {
+        if self.selected().id == id {
+            return;
+        }
+
+        if let Some(tab) = self.get_mut(id) {
+            tab.unread += 1;
+
+            if mention {
+                tab.mentions += 1;
+            }
+        }
+    }
+
+    pub fn selected(&self) -> &Tab<M> {
+        &self.tabs[self.selected]
+    }
+
+    pub fn selected_mut(&mut self) -> &mut Tab<M> {
+        &mut self.tabs[self.selected]
+    }
+
+    /// Returns false if the tab isn't open.
+    pub fn select(&mut self, id: TabId) -> bool {
+        let Some(idx) = self.tabs.iter().position(|tab| tab.id == id) else {
+            return false;
+        };
+
+        self.selected = idx;
+
+        let tab = &mut self.tabs[idx];
+        tab.unread = 0;
+        tab.mentions = 0;
+
+        true
+    }
+
+    /// Selects the tab `step` places away, wrapping around, and returns its ID.
+    pub fn select_adjacent(&mut self, step: isize) -> TabId {
+        let len = self.tabs.len() as isize;
+        let idx = (self.selected as isize + step).rem_euclid(len) as usize;
+        let id = self.tabs[idx].id;
+
+        self.select(id);
+        id
+    }
+
+    /// Closes a tab, the system tab stays. Selects the previous tab if the closed one was selected.
+    pub fn close(&mut self, id: TabId) {
+        let Some(idx) = self.tabs.iter().position(|tab| tab.id == id) else {
+            return;
+        };
+
+        if id == TabId::System {
+            return;
+        }
+
+        self.tabs.remove(idx);
+
+        if self.selected >= idx {
+            self.selected -= 1;
+        }
+    }
+
+    /// Closes all tabs and empties the system tab.
+    pub fn clear(&mut self) {
+        self.tabs.truncate(1);
+        self.tabs[0].messages.clear();
+        self.tabs[0].unread = 0;
+        self.tabs[0].mentions = 0;
+        self.selected = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tabs::{TabId, Tabs};
+
+    #[test]
+    fn test_unread() {
+        let mut tabs: Tabs<&str> = Tabs::new("Dove");
+        tabs.open(TabId::Channel(10), "#general")
+            .messages
+            .push("hi");
+        tabs.mark_unread(TabId::Channel(10), false);
+        tabs.mark_unread(TabId::Channel(10), true);
+
+        let tab = tabs.get(TabId::Channel(10)).unwrap();
+        assert_eq!((tab.unread, tab.mentions), (2, 1));
+
+        // Opening an open tab keeps its messages
+        assert_eq!(tabs.open(TabId::Channel(10), "").messages, vec!["hi"]);
+
+        assert!(tabs.select(TabId::Channel(10)));
+        tabs.mark_unread(TabId::Channel(10), true);
+
+        let tab = tabs.selected();
+        assert_eq!((tab.unread, tab.mentions), (0, 0));
+        assert!(!tabs.select(TabId::Channel(20)));
+    }
+
+    #[test]
+    fn test_switching() {
+        let mut tabs: Tabs<&str> = Tabs::new("Dove");
+        tabs.open(TabId::Channel(10), "#general");
+        tabs.open(TabId::Channel(20), "#random");
+
+        assert_eq!(tabs.select_adjacent(1), TabId::Channel(10));
+        assert_eq!(tabs.select_adjacent(1), TabId::Channel(20));
+        assert_eq!(tabs.select_adjacent(1), TabId::System);
+        assert_eq!(tabs.select_adjacent(-1), TabId::Channel(20));
+
+        tabs.close(TabId::Channel(20));
+        assert_eq!(tabs.selected().id, TabId::Channel(10));
+
+        tabs.close(TabId::System);
+        tabs.clear();
+        assert_eq!(tabs.iter().count(), 1);
+        assert_eq!(tabs.selected().id, TabId::System);
+    }
+}
@@ -1,5 +1,7 @@
 use tokio::sync::mpsc::{self};
 
 pub type MPSCChannel<T> = (mpsc::Sender<T>, mpsc::Receiver<T>);
+/// Sending doesn't need an async context, so the GUI can send in order without spawning tasks.
+pub type UnboundedMPSCChannel<T> = (mpsc::UnboundedSender<T>, mpsc::UnboundedReceiver<T>);
 
 pub const COMM_BUFFER_SIZE: usize = 512;
@@ -1,2 +1,3 @@
 pub mod comm;
+pub mod text;
 pub mod ui;
@@ -0,0 +1,241 @@
+const CODE_FENCE: &str = "```";
+
+/// Splits a message into parts of at most `limit` characters.
+/// Splits at newlines first, then at whitespace, and avoids splitting code blocks.
+/// Code blocks longer than the limit are closed and reopened in the next part.
+pub fn split_message(content: &str, limit: usize) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut rest = content.trim().to_string();
+
+    while rest.chars().count() > limit {
+        let (part, next) = split_once(&rest, limit);
+
+        if !part.trim().is_empty() {
+            parts.push(part);
+        }
+
+        rest = next;
+    }
+
+    if !rest.trim().is_empty() {
+        parts.push(rest);
+    }
+
+    parts
+}
+
+/// Splits off the first word, or the text in double quotes if it starts with one.
+/// Returns None if the closing quote is missing.
+pub fn split_first_arg(text: &str) -> Option<(&str, &str)> {
+    let text = text.trim_start();
+
+    if let Some(quoted) = text.strip_prefix('"') {
+        let (arg, rest) = quoted.split_once('"')?;
+        return Some((arg, rest.trim_start()));
+    }
+
+    match text.split_once(char::is_whitespace) {
+        Some((arg, rest)) => Some((arg, rest.trim_start())),
+        None => Some((text, "")),
+    }
+}
+
+/// Byte index of the character at `char_idx`, or the text length.
+fn byte_index(text: &str, char_idx: usize) -> usize {
+    text.char_indices()
+        .nth(char_idx)
+        .map_or(text.len(), |(idx, _)| idx)
+}
+
+/// Characters with their byte indices up to and including `max`.
+fn chars_until(text: &str, max: usize) -> Vec<(usize, char)> {
+    text.char_indices()
+        .take_while(|(idx, _)| *idx <= max)
+        .collect()
+}
+
+/// Byte ranges of code blocks, including their fences. An unclosed block extends to the end.
+fn code_blocks(text: &str) -> Vec<(usize, usize)> {
+    let fences: Vec<usize> = text.match_indices(CODE_FENCE).map(|(idx, _)| idx).collect();
+
+    fences
+        .chunks(2)
+        .map(|pair| match pair {
+            [start, end] => (*start, end + CODE_FENCE.len()),
+            _ => (pair[0], text.len()),
+        })
+        .collect()
+}
+
+fn split_once(text: &str, limit: usize) -> (String, String) {
+    let max = byte_index(text, limit);
+    let blocks = code_blocks(text);
+    let inside_block = |pos: usize| {
+        blocks
+            .iter()
+            .find(|(start, end)| *start < pos && pos < *end)
+    };
+
+    // Last allowed position within the limit matching the predicate
+    let find_last = |is_boundary: &dyn Fn(usize, char) -> bool| {
+        chars_until(text, max)
+            .into_iter()
+            .rev()
+            .find(|(idx, c)| *idx > 0 && is_boundary(*idx, *c) && inside_block(*idx).is_none())
+            .map(|(idx, _)| idx)
+    };
+
+    let boundary = find_last(&|idx, c| {
+        c == '\n'
+            || blocks
+                .iter()
+                .any(|(start, end)| idx == *start || idx == *end)
+    })
+    .or_else(|| find_last(&|_, c| c.is_whitespace()));
+
+    if let Some(pos) = boundary {
+        return (
+            text[..pos].trim_end().to_string(),
+            text[pos..].trim_start().to_string(),
+        );
+    }
+
+    if let Some((start, _)) = inside_block(max) {
+        return split_code_block(text, *start, limit);
+    }
+
+    (text[..max].to_string(), text[max..].to_string())
+}
+
+/// Splits inside the code block starting at `start`, closing it and reopening it with the same language.
+fn split_code_block(text: &str, start: usize, limit: usize) -> (String, String) {
+    let closing = format!("\n{}", CODE_FENCE);
+    let content_start = start + CODE_FENCE.len();
+
+    // The rest of the opening line is the language, a block on a single line has none
+    let (header_end, language) = match text[content_start..].find('\n') {
+        Some(idx) => (
+            content_start + idx + 1,
+            text[content_start..content_start + idx].trim(),
+        ),
+        None => (content_start, ""),
+    };
+    let header = format!("{}{}\n", CODE_FENCE, language);
+
+    let used = text[..start].chars().count() + header.chars().count() + closing.len();
+    let max = header_end + byte_index(&text[header_end..], limit.saturating_sub(used));
+
+    let cut = chars_until(text, max)
+        .into_iter()
+        .rev()
+        .find(|(idx, c)| *c == '\n' && *idx > header_end)
+        .map_or(max, |(idx, _)| idx);
+
+    let part = format!(
+        "{}{}{}{}",
+        &text[..start],
+        header,
+        text[header_end..cut].trim_end(),
+        closing
+    );
+    let next = format!("{}{}", header, text[cut..].trim_start_matches('\n'));
+
+    // The header alone doesn't fit, split without reopening so the text gets shorter
+    if cut <= header_end || next.len() >= text.len() {
+        let max = byte_index(text, limit);
+        return (text[..max].to_string(), text[max..].to_string());
+    }
+
+    (part, next)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::text::{split_first_arg, split_message};
+
+    #[test]
+    fn test_short_message() {
+        assert_eq!(split_message("Hello", 10), vec!["Hello"]);
+        assert!(split_message("  ", 10).is_empty());
+    }
+
+    #[test]
+    fn test_split_at_newlines_then_whitespace() {
+        assert_eq!(
+            split_message("first line\nsecond line", 15),
+            vec!["first line", "second line"]
+        );
+        assert_eq!(
+            split_message("one two three four", 9),
+            vec!["one two", "three", "four"]
+        );
+        assert_eq!(split_message("abcdefgh", 3), vec!["abc", "def", "gh"]);
+    }
+
+    #[test]
+    fn test_keep_code_blocks_whole() {
+        let content = "Look:\n```rs\nlet a = 1;\nlet b = 2;\n```\ndone";
+
+        assert_eq!(
+            split_message(content, 32),
+            vec!["Look:", "```rs\nlet a = 1;\nlet b = 2;\n```", "done"]
+        );
+    }
+
+    #[test]
+    fn test_reopen_long_code_blocks() {
+        let content = "```rs\nlet a = 1;\nlet b = 2;\nlet c = 3;\n```";
+        let parts = split_message(content, 31);
+
+        assert_eq!(
+            parts,
+            vec![
+                "```rs\nlet a = 1;\nlet b = 2;\n```",
+                "```rs\nlet c = 3;\n```"
+            ]
+        );
+        assert!(parts.iter().all(|part| part.chars().count() <= 31));
+    }
+
+    #[test]
+    fn test_split_single_line_code_blocks() {
+        let content = format!("```{}```", "a".repeat(50));
+        let parts = split_message(&content, 20);
+
+        assert_eq!(parts[0], format!("```\n{}\n```", "a".repeat(12)));
+        assert!(parts.iter().all(|part| part.chars().count() <= 20));
+        assert_eq!(parts.concat().matches('a').count(), 50);
+
+        // Unterminated
+        let content = format!("```{}", "a".repeat(50));
+        let parts = split_message(&content, 20);
+
+        assert!(parts.iter().all(|part| part.chars().count() <= 20));
+        assert_eq!(parts.concat().matches('a').count(), 50);
+
+        // Language longer than the limit
+        let content = format!("```{}\nb\n```", "a".repeat(30));
+        let parts = split_message(&content, 20);
+
+        assert!(parts.iter().all(|part| part.chars().count() <= 20));
+    }
+
+    #[test]
+    fn test_split_first_arg() {
+        assert_eq!(
+            split_first_arg("a.png nice pic"),
+            Some(("a.png", "nice pic"))
+        );
+        assert_eq!(
+            split_first_arg("\"My Pictures/a b.png\" nice"),
+            Some(("My Pictures/a b.png", "nice"))
+        );
+        assert_eq!(
+            split_first_arg("\"/tmp/a  b.png\"  line one\tand  two"),
+            Some(("/tmp/a  b.png", "line one\tand  two"))
+        );
+        assert_eq!(split_first_arg("a.png"), Some(("a.png", "")));
+        assert_eq!(split_first_arg(""), Some(("", "")));
+        assert_eq!(split_first_arg("\"a b.png"), None);
+    }
+}
@@ -1,4 +1,4 @@
-use egui::{Align, FontSelection, Response, RichText, Style, Ui, text::LayoutJob};
+use egui::{Align, Color32, FontSelection, Response, RichText, Style, Ui, text::LayoutJob};
 
 pub fn input_submitted(resp: &Response, ui: &Ui) -> bool {
     resp.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter))
@@ -15,3 +15,26 @@ pub fn combine_rich_text(texts: Vec<impl Into<RichText>>) -> LayoutJob {
 
     layout_job
 }
+
+/// Converts a `0xrrggbb` colour, as used by Discord embeds and profiles.
+pub fn color_from_rgb(rgb: u32) -> Color32 {
+    Color32::from_rgb((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8)
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
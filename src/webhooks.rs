@@ -0,0 +1,107 @@
+use std::collections::BTreeSet;
+
+use crate::{discord::WebhookIdentity, identity::Identity};
+
+/// Channels where messages are sent through a webhook, and the avatar to send with.
+/// The name is the nickname of the [`Identity`].
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct WebhookSettings {
+    pub channels: BTreeSet<u64>,
+    pub avatar_url: Option<String>,
+    /// Name saved before nicknames replaced it, only read to become the nickname
+    pub legacy_name: Option<String>,
+}
+
+impl WebhookSettings {
+    /// Returns None if the channel doesn't use webhook mode or no nickname is set.
+    pub fn identity_for(&self, channel_id: u64, identity: &Identity) -> Option<WebhookIdentity> {
+        if !self.channels.contains(&channel_id) {
+            return None;
+        }
+
+        Some(WebhookIdentity {
+            name: identity.nickname.to_owned()?,
+            avatar_url: self.avatar_url.to_owned(),
+        })
+    }
+
+    /// Parses `avatar <url>`, `channel <id>` and legacy `name <name>` lines.
+    pub fn parse(text: &str) -> Self {
+        let mut res = Self::default();
+
+        for line in text.lines().map(str::trim) {
+            let Some((key, value)) = line.split_once(' ') else {
+                continue;
+            };
+
+            let value = value.trim();
+
+            match key {
+                "avatar" => res.avatar_url = Some(value.to_string()),
+                "name" => res.legacy_name = Some(value.to_string()),
+                "channel" => {
+                    if let Ok(id) = value.parse::<u64>() {
+                        res.channels.insert(id);
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        res
+    }
+
+    pub fn serialize(&self) -> String {
+        let mut lines: Vec<String> = Vec::new();
+
+        if let Some(avatar_url) = &self.avatar_url {
+            lines.push(format!("avatar {}", avatar_url));
+        }
+
+        lines.extend(self.channels.iter().map(|id| format!("channel {}", id)));
+
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{identity::Identity, webhooks::WebhookSettings};
+
+    #[test]
+    fn test_identity() {
+        let mut settings = WebhookSettings::default();
+        settings.channels.insert(10);
+
+        let mut identity = Identity::default();
+        assert_eq!(settings.identity_for(10, &identity), None);
+
+        identity.nickname = Some("Wolfyxon".to_string());
+
+        assert_eq!(
+            settings.identity_for(10, &identity).unwrap().name,
+            "Wolfyxon"
+        );
+        assert_eq!(settings.identity_for(20, &identity), None);
+    }
+
+    #[test]
+    fn test_serialization() {
+        let mut settings = WebhookSettings {
+            avatar_url: Some("https://example.com/dove.png".to_string()),
+            ..Default::default()
+        };
+        settings.channels.insert(10);
+        settings.channels.insert(20);
+
+        assert_eq!(WebhookSettings::parse(&settings.serialize()), settings);
+        assert_eq!(
+            WebhookSettings::parse("garbage\nchannel nope"),
+            WebhookSettings::default()
+        );
+
+        let legacy = WebhookSettings::parse("name Wolfyxon\nchannel 10");
+        assert_eq!(legacy.legacy_name.as_deref(), Some("Wolfyxon"));
+        assert!(!legacy.serialize().contains("Wolfyxon"));
+    }
+}